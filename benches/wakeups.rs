@@ -0,0 +1,226 @@
+// phasutils: 为Steam游戏恐鬼症设计的实用工具
+// Copyright (C) 2024  Chen Siyuan
+//
+// This program is free software: you can redistribute it and/or modify
+// it under the terms of the GNU General Public License as published by
+// the Free Software Foundation, either version 3 of the License, or
+// (at your option) any later version.
+//
+// This program is distributed in the hope that it will be useful,
+// but WITHOUT ANY WARRANTY; without even the implied warranty of
+// MERCHANTABILITY or FITNESS FOR A PARTICULAR PURPOSE.  See the
+// GNU General Public License for more details.
+//
+// You should have received a copy of the GNU General Public License
+// along with this program.  If not, see <https://www.gnu.org/licenses/>.
+
+// 比较改动前的固定帧率主循环和按需重绘在几种情况下的唤醒、重绘次数和忙碌时间
+// 运行：cargo bench --bench wakeups
+
+use std::hint::black_box;
+use std::sync::mpsc;
+use std::{thread, time};
+
+use phasutils::app::{AppState, Command};
+use phasutils::config::{Config, TimerConfig};
+use phasutils::input::{Bindings, Trigger};
+use phasutils::redraw::RedrawScheduler;
+use phasutils::source::InputEvent;
+
+const DURATION: time::Duration = time::Duration::from_secs(3);
+// 改动前的 `set_framerate_limit(200)`
+const FIXED_FRAME_RATE: u32 = 200;
+const IDLE_FRAME_RATE: u32 = 10;
+// 按住按键时系统重复按键的间隔
+const KEY_REPEAT_INTERVAL: time::Duration = time::Duration::from_millis(33);
+
+fn state(show_tenths: bool, running: bool) -> AppState {
+    let mut state = AppState::new(Config {
+        version: 2,
+        bindings: Bindings::default(),
+        timers: vec![TimerConfig {
+            id: "main".to_string(),
+            start: Some(rdev::Key::Num1.into()),
+            show_tenths,
+            ..Default::default()
+        }],
+        presets: vec![],
+        idle_frame_rate: IDLE_FRAME_RATE,
+        auto_scroll_seconds: 8,
+        ghosts: vec![],
+    });
+
+    if running {
+        let now = time::Instant::now();
+        for event in [
+            InputEvent::Press(Trigger::Key(rdev::Key::Num1)),
+            InputEvent::Release(Trigger::Key(rdev::Key::Num1)),
+        ] {
+            state.reduce(Command::Input(event, now));
+        }
+    }
+
+    state
+}
+
+// 游戏中按住 W 走路、在聊天框打字、点击鼠标，都没有绑定热键
+fn play(command_sender: mpsc::Sender<Command>) {
+    let chat = [
+        rdev::Key::KeyH,
+        rdev::Key::KeyE,
+        rdev::Key::KeyL,
+        rdev::Key::KeyO,
+    ];
+    let start = time::Instant::now();
+    let mut tick = 0;
+    while start.elapsed() < DURATION {
+        let mut events = vec![InputEvent::Press(Trigger::Key(rdev::Key::KeyW))];
+        if tick % 5 == 0 {
+            let key = Trigger::Key(chat[tick / 5 % chat.len()]);
+            events.extend([InputEvent::Press(key), InputEvent::Release(key)]);
+        }
+        if tick % 15 == 0 {
+            let button = Trigger::Mouse(rdev::Button::Left);
+            events.extend([InputEvent::Press(button), InputEvent::Release(button)]);
+        }
+
+        for event in events {
+            if command_sender
+                .send(Command::Input(event, time::Instant::now()))
+                .is_err()
+            {
+                return;
+            }
+        }
+        tick += 1;
+        thread::sleep(KEY_REPEAT_INTERVAL);
+    }
+}
+
+fn commands(playing: bool) -> mpsc::Receiver<Command> {
+    let (command_sender, command_receiver) = mpsc::channel();
+    if playing {
+        thread::spawn(move || play(command_sender));
+    } else {
+        // 保持发送端存在，与没有输入时的主循环相同
+        thread::spawn(move || {
+            thread::sleep(DURATION);
+            drop(command_sender);
+        });
+    }
+
+    command_receiver
+}
+
+// 重绘时读取状态生成所有要显示的文字，界面的其余工作与这部分成正比
+fn render(state: &AppState) {
+    let now = time::Instant::now();
+    for timer in state.timers() {
+        black_box(timer.text());
+        black_box(timer.lap_lines());
+        black_box(timer.is_flashing());
+    }
+    black_box(state.tips());
+    black_box(state.candidates());
+    black_box(state.speed());
+    black_box(state.quit_confirmation().remaining(now));
+}
+
+struct Report {
+    wakeups: u32,
+    redraws: u32,
+    // 不在等待中的时间
+    busy: time::Duration,
+}
+
+// 改动前的主循环：每帧取出所有命令、更新状态并重绘，由帧率限制休眠
+fn fixed(mut state: AppState, command_receiver: mpsc::Receiver<Command>) -> Report {
+    let interval = time::Duration::from_secs(1) / FIXED_FRAME_RATE;
+    let start = time::Instant::now();
+    let mut report = Report {
+        wakeups: 0,
+        redraws: 0,
+        busy: time::Duration::ZERO,
+    };
+    while start.elapsed() < DURATION {
+        let frame = time::Instant::now();
+        for command in command_receiver.try_iter() {
+            state.reduce(command);
+        }
+        state.reduce(Command::Tick(time::Instant::now()));
+        render(&state);
+        report.wakeups += 1;
+        report.redraws += 1;
+
+        let busy = frame.elapsed();
+        report.busy += busy;
+        thread::sleep(interval.saturating_sub(busy));
+    }
+
+    report
+}
+
+// 与 main.rs 的主循环相同，只在状态变化、计时器进位和空闲帧时醒来
+fn scheduled(mut state: AppState, command_receiver: mpsc::Receiver<Command>) -> Report {
+    let mut redraw = RedrawScheduler::new(IDLE_FRAME_RATE);
+    let start = time::Instant::now();
+    let mut report = Report {
+        wakeups: 0,
+        redraws: 0,
+        busy: time::Duration::ZERO,
+    };
+    while start.elapsed() < DURATION {
+        let timeout = redraw.timeout(time::Instant::now());
+        let received = command_receiver.recv_timeout(timeout);
+        let frame = time::Instant::now();
+        match received {
+            Ok(command) => state.reduce(command),
+            Err(mpsc::RecvTimeoutError::Timeout) => {}
+            Err(mpsc::RecvTimeoutError::Disconnected) => break,
+        }
+        for command in command_receiver.try_iter() {
+            state.reduce(command);
+        }
+        report.wakeups += 1;
+
+        let now = time::Instant::now();
+        state.reduce(Command::Tick(now));
+        if redraw.should_redraw(&state, now) {
+            render(&state);
+            report.redraws += 1;
+        }
+        report.busy += frame.elapsed();
+    }
+
+    report
+}
+
+fn main() {
+    let print = |name: &str, report: Report| {
+        let seconds = DURATION.as_secs_f32();
+        println!(
+            "{:<28} 唤醒 {:>7.1} 次/秒  重绘 {:>7.1} 次/秒  忙碌 {:>7.3} 毫秒/秒",
+            name,
+            report.wakeups as f32 / seconds,
+            report.redraws as f32 / seconds,
+            report.busy.as_secs_f32() * 1000.0 / seconds
+        );
+    };
+
+    for (name, show_tenths, running) in [
+        ("计时器停止", false, false),
+        ("计时器运行", false, true),
+        ("显示十分之一秒", true, true),
+    ] {
+        for (input, playing) in [("", false), (" 游戏中输入", true)] {
+            print(
+                &format!("固定 {} 帧 {}{}", FIXED_FRAME_RATE, name, input),
+                fixed(state(show_tenths, running), commands(playing)),
+            );
+            print(
+                &format!("按需重绘 {}{}", name, input),
+                scheduled(state(show_tenths, running), commands(playing)),
+            );
+        }
+    }
+}
@@ -0,0 +1,692 @@
+// phasutils: 为Steam游戏恐鬼症设计的实用工具
+// Copyright (C) 2024  Chen Siyuan
+//
+// This program is free software: you can redistribute it and/or modify
+// it under the terms of the GNU General Public License as published by
+// the Free Software Foundation, either version 3 of the License, or
+// (at your option) any later version.
+//
+// This program is distributed in the hope that it will be useful,
+// but WITHOUT ANY WARRANTY; without even the implied warranty of
+// MERCHANTABILITY or FITNESS FOR A PARTICULAR PURPOSE.  See the
+// GNU General Public License for more details.
+//
+// You should have received a copy of the GNU General Public License
+// along with this program.  If not, see <https://www.gnu.org/licenses/>.
+
+use std::time;
+
+use log::debug;
+
+use crate::config::{Config, GhostInformation};
+use crate::deduction::Investigation;
+use crate::input::{Action, Confirmation, Hotkey, ModifierState, Trigger};
+use crate::source::InputEvent;
+use crate::speed::{SpeedEstimate, TapTempo};
+use crate::timer::Timer;
+
+// 所有状态变化都通过 `AppState::reduce` 按顺序执行
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub enum Command {
+    // 输入线程收到的按键和收到的时间
+    Input(InputEvent, time::Instant),
+    // 每帧发送一次，处理按住确认等与时间有关的状态
+    Tick(time::Instant),
+    // 悬浮窗被关闭
+    Close,
+    // 界面排版后报告鬼魂特性的页数
+    FeaturesLaidOut { pages: usize },
+}
+
+// 不依赖窗口的程序状态，命令进入、供界面读取的状态输出
+pub struct AppState {
+    config: Config,
+    timers: Vec<Timer>,
+    investigation: Investigation,
+    tap_tempo: TapTempo,
+    // `ghost_index` 是在剩余候选鬼魂中的位置
+    ghost_index: usize,
+    // 鬼魂特性当前显示的页和总页数，切换鬼魂时回到第一页
+    features_page: usize,
+    features_pages: usize,
+    // 开启自动翻页或上一次自动翻页的时间
+    auto_scroll: Option<time::Instant>,
+    armed: bool,
+    hidden: bool,
+    // 精简模式只显示计时器和候选数量
+    compact: bool,
+    dragging: bool,
+    should_close: bool,
+    quit_confirmation: Confirmation,
+    modifier_state: ModifierState,
+    // 每次状态变化时加一，界面据此判断是否需要重绘
+    revision: u64,
+}
+
+// 退出确认倒计时的显示精度
+const CONFIRMATION_DISPLAY_INTERVAL: time::Duration = time::Duration::from_millis(100);
+// 拖动时跟随鼠标的间隔
+const DRAG_INTERVAL: time::Duration = time::Duration::from_millis(16);
+
+impl AppState {
+    pub fn new(config: Config) -> AppState {
+        let timers = config
+            .timers
+            .iter()
+            .map(|timer_config| Timer::new(timer_config.clone(), &config.presets))
+            .collect();
+        let quit_confirmation = Confirmation::new(config.bindings.quit_confirm);
+
+        AppState {
+            config,
+            timers,
+            investigation: Investigation::new(),
+            tap_tempo: TapTempo::new(),
+            ghost_index: 0,
+            features_page: 0,
+            features_pages: 1,
+            auto_scroll: None,
+            armed: true,
+            hidden: false,
+            compact: false,
+            dragging: false,
+            should_close: false,
+            quit_confirmation,
+            modifier_state: ModifierState::new(),
+            revision: 0,
+        }
+    }
+
+    pub fn reduce(&mut self, command: Command) {
+        match command {
+            Command::Input(event, at) => {
+                debug!("{:?}", event);
+                if self.handle_input(event, at) {
+                    self.revision += 1;
+                }
+            }
+            Command::Tick(now) => {
+                if self.quit_confirmation.poll(now) {
+                    self.should_close = true;
+                    self.revision += 1;
+                }
+                if let Some(scrolled_at) = self.auto_scroll {
+                    if now.duration_since(scrolled_at) >= self.auto_scroll_interval() {
+                        self.features_page = (self.features_page + 1) % self.features_pages;
+                        self.auto_scroll = Some(now);
+                        self.revision += 1;
+                    }
+                }
+            }
+            Command::Close => {
+                debug!("悬浮窗被关闭");
+                self.should_close = true;
+                self.revision += 1;
+            }
+            // 界面在同一帧里读取结果，不需要再重绘
+            Command::FeaturesLaidOut { pages } => {
+                self.features_pages = pages.max(1);
+                self.features_page = self.features_page.min(self.features_pages - 1);
+            }
+        }
+    }
+
+    fn auto_scroll_interval(&self) -> time::Duration {
+        time::Duration::from_secs(self.config.auto_scroll_seconds.max(1))
+    }
+
+    // 返回显示的状态是否变化；游戏中的移动、聊天和没有绑定的按键都不需要重绘
+    fn handle_input(&mut self, event: InputEvent, now: time::Instant) -> bool {
+        let trigger = match event {
+            InputEvent::Press(trigger) => trigger,
+            InputEvent::Release(trigger) => {
+                let mut changed = false;
+                if let Trigger::Key(key) = trigger {
+                    self.modifier_state.release(key);
+                }
+                if self.config.bindings.quit.map(|quit| quit.trigger) == Some(trigger) {
+                    let remaining = self.quit_confirmation.remaining(now);
+                    self.quit_confirmation.release();
+                    changed |= self.quit_confirmation.remaining(now) != remaining;
+                }
+                if self.config.bindings.drag.map(|drag| drag.trigger) == Some(trigger) {
+                    changed |= self.dragging;
+                    self.dragging = false;
+                }
+                return changed;
+            }
+        };
+
+        // 按下的修饰键本身不算在组合里
+        let hotkey = Hotkey::new(trigger, self.modifier_state.modifiers());
+        if let Trigger::Key(key) = trigger {
+            self.modifier_state.press(key);
+        }
+
+        let action = self.config.bindings.action(hotkey);
+        if action == Some(Action::ToggleArmed) {
+            self.armed = !self.armed;
+            return true;
+        }
+        if !self.armed {
+            return false;
+        }
+
+        let mut changed = false;
+        for timer in &mut self.timers {
+            changed |= timer.handle_hotkey(hotkey);
+        }
+
+        let ghost = self.current_ghost().map(|(_, ghost)| ghost.id.clone());
+
+        match action {
+            Some(Action::TapSpeed) => {
+                self.tap_tempo.tap(now);
+                self.investigation.set_speed(self.tap_tempo.estimate());
+            }
+            Some(Action::Quit) => {
+                if self.quit_confirmation.press(now) {
+                    self.should_close = true;
+                }
+            }
+            Some(Action::ToggleHidden) => self.hidden = !self.hidden,
+            Some(Action::ToggleCompact) => self.compact = !self.compact,
+            Some(Action::Drag) => self.dragging = true,
+            Some(Action::PreviousGhost) => self.ghost_index = self.ghost_index.saturating_sub(1),
+            Some(Action::NextGhost) => self.ghost_index += 1,
+            Some(Action::CycleEvidenceCount) => self.investigation.cycle_evidence_count(),
+            Some(Action::ResetInvestigation) => {
+                self.tap_tempo.clear();
+                self.investigation.reset();
+            }
+            Some(Action::CycleEvidence(evidence)) => self.investigation.cycle_state(evidence),
+            Some(Action::PreviousFeaturesPage) => {
+                self.features_page = self.features_page.saturating_sub(1);
+                self.auto_scroll = self.auto_scroll.map(|_| now);
+            }
+            Some(Action::NextFeaturesPage) => {
+                self.features_page = (self.features_page + 1).min(self.features_pages - 1);
+                self.auto_scroll = self.auto_scroll.map(|_| now);
+            }
+            Some(Action::ToggleAutoScroll) => {
+                self.auto_scroll = match self.auto_scroll {
+                    Some(_) => None,
+                    None => Some(now),
+                };
+            }
+            Some(Action::ToggleArmed) | None => return changed,
+        }
+
+        // 候选变少时停在最后一个
+        let candidate_count = self.candidates().len();
+        self.ghost_index = self.ghost_index.min(candidate_count.saturating_sub(1));
+        if self.current_ghost().map(|(_, ghost)| ghost.id.clone()) != ghost {
+            self.features_page = 0;
+            self.auto_scroll = self.auto_scroll.map(|_| now);
+        }
+
+        true
+    }
+
+    pub fn revision(&self) -> u64 {
+        self.revision
+    }
+
+    // 没有新命令时，显示内容下一次变化前的时间
+    pub fn next_change(&self, now: time::Instant) -> Option<time::Duration> {
+        let confirmation = self
+            .quit_confirmation
+            .remaining(now)
+            .map(|remaining| remaining.min(CONFIRMATION_DISPLAY_INTERVAL));
+        let auto_scroll = self.auto_scroll.map(|scrolled_at| {
+            self.auto_scroll_interval()
+                .saturating_sub(now.duration_since(scrolled_at))
+        });
+
+        self.timers
+            .iter()
+            .filter_map(Timer::next_change)
+            .chain(confirmation)
+            .chain(auto_scroll)
+            .chain(self.dragging.then_some(DRAG_INTERVAL))
+            .min()
+    }
+
+    pub fn config(&self) -> &Config {
+        &self.config
+    }
+
+    pub fn timers(&self) -> &[Timer] {
+        &self.timers
+    }
+
+    pub fn investigation(&self) -> &Investigation {
+        &self.investigation
+    }
+
+    pub fn speed(&self) -> Option<SpeedEstimate> {
+        self.tap_tempo.estimate()
+    }
+
+    pub fn features_page(&self) -> usize {
+        self.features_page
+    }
+
+    pub fn features_pages(&self) -> usize {
+        self.features_pages
+    }
+
+    pub fn is_auto_scrolling(&self) -> bool {
+        self.auto_scroll.is_some()
+    }
+
+    pub fn is_armed(&self) -> bool {
+        self.armed
+    }
+
+    pub fn is_hidden(&self) -> bool {
+        self.hidden
+    }
+
+    pub fn is_compact(&self) -> bool {
+        self.compact
+    }
+
+    pub fn is_dragging(&self) -> bool {
+        self.dragging
+    }
+
+    pub fn should_close(&self) -> bool {
+        self.should_close
+    }
+
+    pub fn quit_confirmation(&self) -> &Confirmation {
+        &self.quit_confirmation
+    }
+
+    pub fn candidates(&self) -> Vec<usize> {
+        self.investigation.candidates(&self.config.ghosts)
+    }
+
+    // 当前显示的鬼魂和它在候选中的位置
+    pub fn current_ghost(&self) -> Option<(usize, &GhostInformation)> {
+        self.candidates()
+            .get(self.ghost_index)
+            .map(|index| (self.ghost_index, &self.config.ghosts[*index]))
+    }
+
+    // 计时器运行时高亮对应的开始提示
+    pub fn tips(&self) -> Vec<(String, Option<usize>)> {
+        let mut tips: Vec<(String, Option<usize>)> = vec![];
+        for (idx, timer) in self.timers.iter().enumerate() {
+            let timer_config = timer.config();
+            // 没有名称的倒计时与 `Timer::text` 一样使用预设名称
+            let name = match timer.preset() {
+                _ if !timer_config.name.is_empty() => &timer_config.name,
+                Some(preset) => &preset.name,
+                None => "计时",
+            };
+
+            if let Some(key) = timer_config.start {
+                tips.push((format!("[{}] 键开始{}", key.name(), name), Some(idx)));
+            }
+            if let Some(key) = timer_config.stop {
+                tips.push((format!("[{}] 键停止{}", key.name(), name), None));
+            }
+            if let Some(key) = timer_config.reset {
+                tips.push((format!("[{}] 键重置{}", key.name(), name), None));
+            }
+            if let Some(key) = timer_config.restart {
+                tips.push((format!("[{}] 键重新开始{}", key.name(), name), Some(idx)));
+            }
+            if let Some(key) = timer_config.next_preset {
+                tips.push((format!("[{}] 键切换{}预设", key.name(), name), None));
+            }
+            if let Some(key) = timer_config.lap {
+                tips.push((format!("[{}] 键记录{}分段", key.name(), name), None));
+            }
+            if let Some(key) = timer_config.clear_laps {
+                tips.push((format!("[{}] 键清除{}分段", key.name(), name), None));
+            }
+            if let (Some(up), Some(down)) =
+                (timer_config.lap_scroll_up, timer_config.lap_scroll_down)
+            {
+                tips.push((
+                    format!("[{}/{}] 键滚动{}分段", up.name(), down.name(), name),
+                    None,
+                ));
+            }
+        }
+        for tip in self.config.bindings.tips() {
+            tips.push((tip, None));
+        }
+
+        tips
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::config::{CountdownPreset, TimerConfig};
+    use crate::deduction::EvidenceState;
+    use crate::evidence::Evidence;
+    use crate::input::{Bindings, ConfirmMode, Modifiers};
+
+    fn ghost(id: &str, evidence: &[Evidence]) -> GhostInformation {
+        GhostInformation {
+            id: id.to_string(),
+            name: id.to_string(),
+            speed: String::new(),
+            features: String::new(),
+            evidence: evidence.to_vec(),
+            forced_evidence: vec![],
+            speed_profile: None,
+        }
+    }
+
+    fn app() -> AppState {
+        use Evidence::*;
+
+        AppState::new(Config {
+            version: 2,
+            bindings: Bindings {
+                quit_confirm: ConfirmMode::Press,
+                ..Default::default()
+            },
+            timers: vec![TimerConfig {
+                id: "main".to_string(),
+                start: Some(rdev::Key::Num1.into()),
+                ..Default::default()
+            }],
+            presets: vec![],
+            idle_frame_rate: 10,
+            auto_scroll_seconds: 8,
+            ghosts: vec![
+                ghost("spirit", &[Emf5, SpiritBox, GhostWriting]),
+                ghost("wraith", &[Emf5, SpiritBox, Dots]),
+                ghost("phantom", &[SpiritBox, Fingerprints, Dots]),
+            ],
+        })
+    }
+
+    fn input(app: &mut AppState, event: InputEvent) {
+        app.reduce(Command::Input(event, time::Instant::now()));
+    }
+
+    fn press(app: &mut AppState, key: rdev::Key) {
+        input(app, InputEvent::Press(Trigger::Key(key)));
+        input(app, InputEvent::Release(Trigger::Key(key)));
+    }
+
+    fn current_id(app: &AppState) -> Option<String> {
+        app.current_ghost().map(|(_, ghost)| ghost.id.clone())
+    }
+
+    #[test]
+    fn unnamed_countdown_tips_use_preset_name() {
+        let app = AppState::new(Config {
+            version: 2,
+            bindings: Bindings::default(),
+            timers: vec![
+                TimerConfig {
+                    id: "main".to_string(),
+                    start: Some(rdev::Key::Num1.into()),
+                    ..Default::default()
+                },
+                TimerConfig {
+                    id: "hunt_cooldown".to_string(),
+                    restart: Some(rdev::Key::Num5.into()),
+                    presets: vec!["hunt_cooldown".to_string()],
+                    ..Default::default()
+                },
+            ],
+            presets: vec![CountdownPreset {
+                id: "hunt_cooldown".to_string(),
+                name: "猎杀冷却".to_string(),
+                seconds: 25,
+                thresholds: vec![],
+            }],
+            idle_frame_rate: 10,
+            auto_scroll_seconds: 8,
+            ghosts: vec![],
+        });
+        let tips: Vec<String> = app.tips().into_iter().map(|(tip, _)| tip).collect();
+        assert!(tips.contains(&"[1] 键开始计时".to_string()));
+        assert!(tips.contains(&"[5] 键重新开始猎杀冷却".to_string()));
+        assert!(!tips.contains(&"[5] 键重新开始计时".to_string()));
+    }
+
+    #[test]
+    fn evidence_keys_filter_candidates() {
+        let mut app = app();
+        press(&mut app, rdev::Key::F1);
+        assert_eq!(
+            app.investigation().state(Evidence::Emf5),
+            EvidenceState::Confirmed
+        );
+        assert_eq!(app.candidates(), vec![0, 1]);
+    }
+
+    #[test]
+    fn ghost_index_stays_within_candidates() {
+        let mut app = app();
+        press(&mut app, rdev::Key::KeyZ);
+        assert_eq!(current_id(&app).as_deref(), Some("spirit"));
+
+        for _ in 0..5 {
+            press(&mut app, rdev::Key::KeyX);
+        }
+        assert_eq!(current_id(&app).as_deref(), Some("phantom"));
+
+        // 确认 EMF5 后幻影被排除，停在最后一个候选
+        press(&mut app, rdev::Key::F1);
+        assert_eq!(current_id(&app).as_deref(), Some("wraith"));
+    }
+
+    #[test]
+    fn disarmed_hotkeys_are_ignored() {
+        let mut app = app();
+        press(&mut app, rdev::Key::ScrollLock);
+        assert!(!app.is_armed());
+
+        press(&mut app, rdev::Key::Num1);
+        press(&mut app, rdev::Key::F1);
+        assert!(!app.timers()[0].is_running());
+        assert_eq!(app.candidates(), vec![0, 1, 2]);
+
+        press(&mut app, rdev::Key::ScrollLock);
+        press(&mut app, rdev::Key::Num1);
+        assert!(app.timers()[0].is_running());
+    }
+
+    #[test]
+    fn modifiers_are_part_of_the_hotkey() {
+        let mut app = app();
+        let now = time::Instant::now();
+        app.handle_input(InputEvent::Press(Trigger::Key(rdev::Key::ControlLeft)), now);
+        app.handle_input(InputEvent::Press(Trigger::Key(rdev::Key::F1)), now);
+        assert_eq!(
+            app.investigation().state(Evidence::Emf5),
+            EvidenceState::Unknown
+        );
+
+        input(
+            &mut app,
+            InputEvent::Release(Trigger::Key(rdev::Key::ControlLeft)),
+        );
+        input(&mut app, InputEvent::Press(Trigger::Key(rdev::Key::F1)));
+        assert_eq!(
+            app.investigation().state(Evidence::Emf5),
+            EvidenceState::Confirmed
+        );
+    }
+
+    #[test]
+    fn quit_key_closes() {
+        let mut app = app();
+        assert!(!app.should_close());
+        press(&mut app, rdev::Key::Num0);
+        assert!(app.should_close());
+    }
+
+    #[test]
+    fn hold_quit_closes_on_tick() {
+        let mut app = app();
+        app.quit_confirmation = Confirmation::new(ConfirmMode::Hold { millis: 1000 });
+        let start = time::Instant::now();
+        let quit = InputEvent::Press(Trigger::Key(rdev::Key::Num0));
+
+        app.reduce(Command::Input(quit, start));
+        app.reduce(Command::Tick(start + time::Duration::from_millis(500)));
+        assert!(!app.should_close());
+
+        // 松开后重新计时
+        app.reduce(Command::Input(
+            InputEvent::Release(Trigger::Key(rdev::Key::Num0)),
+            start + time::Duration::from_millis(600),
+        ));
+        app.reduce(Command::Input(
+            quit,
+            start + time::Duration::from_millis(700),
+        ));
+        app.reduce(Command::Tick(start + time::Duration::from_millis(1500)));
+        assert!(!app.should_close());
+
+        app.reduce(Command::Tick(start + time::Duration::from_millis(1700)));
+        assert!(app.should_close());
+    }
+
+    #[test]
+    fn double_press_quit_ignores_held_key() {
+        let mut app = app();
+        app.quit_confirmation = Confirmation::new(ConfirmMode::DoublePress { millis: 500 });
+        let start = time::Instant::now();
+        let quit = InputEvent::Press(Trigger::Key(rdev::Key::Num0));
+
+        // 按住退出键时的重复按键不会退出
+        for repeat in 0..5 {
+            app.reduce(Command::Input(
+                quit,
+                start + time::Duration::from_millis(repeat * 30),
+            ));
+        }
+        assert!(!app.should_close());
+
+        app.reduce(Command::Input(
+            InputEvent::Release(Trigger::Key(rdev::Key::Num0)),
+            start + time::Duration::from_millis(200),
+        ));
+        app.reduce(Command::Input(
+            quit,
+            start + time::Duration::from_millis(300),
+        ));
+        assert!(app.should_close());
+    }
+
+    #[test]
+    fn unbound_input_keeps_revision() {
+        let mut app = app();
+        let revision = app.revision();
+        press(&mut app, rdev::Key::KeyW);
+        press(&mut app, rdev::Key::ShiftLeft);
+        input(
+            &mut app,
+            InputEvent::Press(Trigger::Mouse(rdev::Button::Left)),
+        );
+        input(
+            &mut app,
+            InputEvent::Release(Trigger::Mouse(rdev::Button::Left)),
+        );
+        assert_eq!(app.revision(), revision);
+
+        press(&mut app, rdev::Key::F1);
+        assert_eq!(app.revision(), revision + 1);
+        press(&mut app, rdev::Key::Num1);
+        assert_eq!(app.revision(), revision + 2);
+    }
+
+    #[test]
+    fn features_pages() {
+        let mut app = app();
+        app.reduce(Command::FeaturesLaidOut { pages: 3 });
+        for _ in 0..5 {
+            press(&mut app, rdev::Key::PageDown);
+        }
+        assert_eq!(app.features_page(), 2);
+
+        press(&mut app, rdev::Key::PageUp);
+        assert_eq!(app.features_page(), 1);
+
+        // 页数变少时停在最后一页
+        app.reduce(Command::FeaturesLaidOut { pages: 1 });
+        assert_eq!(app.features_page(), 0);
+
+        app.reduce(Command::FeaturesLaidOut { pages: 3 });
+        press(&mut app, rdev::Key::PageDown);
+        press(&mut app, rdev::Key::KeyX);
+        assert_eq!(app.features_page(), 0);
+    }
+
+    #[test]
+    fn auto_scroll_wraps_around() {
+        let mut app = app();
+        app.config.bindings.auto_scroll = Some(rdev::Key::End.into());
+        app.reduce(Command::FeaturesLaidOut { pages: 2 });
+
+        let start = time::Instant::now();
+        app.reduce(Command::Input(
+            InputEvent::Press(Trigger::Key(rdev::Key::End)),
+            start,
+        ));
+        assert!(app.is_auto_scrolling());
+
+        app.reduce(Command::Tick(start + time::Duration::from_secs(7)));
+        assert_eq!(app.features_page(), 0);
+        app.reduce(Command::Tick(start + time::Duration::from_secs(8)));
+        assert_eq!(app.features_page(), 1);
+        app.reduce(Command::Tick(start + time::Duration::from_secs(16)));
+        assert_eq!(app.features_page(), 0);
+    }
+
+    #[test]
+    fn drag_while_held() {
+        let mut app = app();
+        app.config.bindings.drag = Some(Hotkey::new(
+            Trigger::Key(rdev::Key::KeyD),
+            Modifiers {
+                ctrl: true,
+                ..Default::default()
+            },
+        ));
+
+        input(
+            &mut app,
+            InputEvent::Press(Trigger::Key(rdev::Key::ControlLeft)),
+        );
+        input(&mut app, InputEvent::Press(Trigger::Key(rdev::Key::KeyD)));
+        assert!(app.is_dragging());
+        // 先松开修饰键也继续拖动
+        input(
+            &mut app,
+            InputEvent::Release(Trigger::Key(rdev::Key::ControlLeft)),
+        );
+        assert!(app.is_dragging());
+        input(&mut app, InputEvent::Release(Trigger::Key(rdev::Key::KeyD)));
+        assert!(!app.is_dragging());
+    }
+
+    #[test]
+    fn toggle_compact_mode() {
+        let mut app = app();
+        app.config.bindings.compact = Some(rdev::Key::KeyC.into());
+        let revision = app.revision();
+
+        input(&mut app, InputEvent::Press(Trigger::Key(rdev::Key::KeyC)));
+        assert!(app.is_compact());
+        assert!(app.revision() > revision);
+        input(&mut app, InputEvent::Release(Trigger::Key(rdev::Key::KeyC)));
+        input(&mut app, InputEvent::Press(Trigger::Key(rdev::Key::KeyC)));
+        assert!(!app.is_compact());
+    }
+}
@@ -0,0 +1,286 @@
+// phasutils: 为Steam游戏恐鬼症设计的实用工具
+// Copyright (C) 2024  Chen Siyuan
+//
+// This program is free software: you can redistribute it and/or modify
+// it under the terms of the GNU General Public License as published by
+// the Free Software Foundation, either version 3 of the License, or
+// (at your option) any later version.
+//
+// This program is distributed in the hope that it will be useful,
+// but WITHOUT ANY WARRANTY; without even the implied warranty of
+// MERCHANTABILITY or FITNESS FOR A PARTICULAR PURPOSE.  See the
+// GNU General Public License for more details.
+//
+// You should have received a copy of the GNU General Public License
+// along with this program.  If not, see <https://www.gnu.org/licenses/>.
+
+use std::{env, error, fs, path};
+
+use log::warn;
+use serde::{Deserialize, Serialize};
+
+use crate::evidence::Evidence;
+use crate::input::{Bindings, Hotkey};
+use crate::speed::SpeedProfile;
+
+pub const CONFIG_VERSION: i32 = 2;
+pub const CONFIG_FILE: &str = "config.json";
+
+// 配置文件所在的目录，设置文件和相对路径的字体也放在这里；
+// 优先使用程序所在的目录，开发时 `cargo run` 找不到再使用工作目录
+pub fn config_dir() -> path::PathBuf {
+    let exe_dir = env::current_exe()
+        .ok()
+        .and_then(|exe| exe.parent().map(path::Path::to_path_buf));
+
+    exe_dir
+        .iter()
+        .cloned()
+        .chain(env::current_dir().ok())
+        .find(|dir| dir.join(CONFIG_FILE).is_file())
+        .or(exe_dir)
+        .unwrap_or_default()
+}
+
+#[derive(Serialize, Deserialize, Debug)]
+pub struct GhostInformation {
+    pub id: String,
+    pub name: String,
+    // 版本 2 起由 `speed_profile` 生成
+    #[serde(default)]
+    pub speed: String,
+    pub features: String,
+    // 版本 0 的配置文件没有以下字段
+    #[serde(default)]
+    pub evidence: Vec<Evidence>,
+    // 证据减少时也一定会出现的证据（如寒魔的冰点）
+    // 不在 `evidence` 中的必出证据是额外的假证据（如拟魂的灵球）
+    #[serde(default)]
+    pub forced_evidence: Vec<Evidence>,
+    // 版本 1 及以前的配置文件没有速度数据
+    #[serde(default)]
+    pub speed_profile: Option<SpeedProfile>,
+}
+
+impl GhostInformation {
+    pub fn has_evidence(&self, evidence: Evidence) -> bool {
+        self.evidence.contains(&evidence) || self.forced_evidence.contains(&evidence)
+    }
+
+    pub fn speed_text(&self) -> String {
+        match &self.speed_profile {
+            Some(speed_profile) => speed_profile.description(),
+            None => self.speed.clone(),
+        }
+    }
+
+    pub fn evidence_text(&self) -> String {
+        Evidence::ALL
+            .iter()
+            .filter(|evidence| self.has_evidence(**evidence))
+            .map(|evidence| {
+                if self.forced_evidence.contains(evidence) {
+                    format!("{}*", evidence.name())
+                } else {
+                    evidence.name().to_string()
+                }
+            })
+            .collect::<Vec<_>>()
+            .join(" / ")
+    }
+}
+
+#[derive(Serialize, Deserialize, Debug, Clone)]
+pub struct TimerThreshold {
+    pub seconds: u64,
+    pub label: String,
+}
+
+#[derive(Serialize, Deserialize, Debug, Clone)]
+pub struct TimerConfig {
+    pub id: String,
+    // 为空时只显示时间
+    pub name: String,
+    #[serde(default = "default_timer_font_size")]
+    pub font_size: u32,
+    #[serde(default)]
+    pub start: Option<Hotkey>,
+    #[serde(default)]
+    pub stop: Option<Hotkey>,
+    #[serde(default)]
+    pub reset: Option<Hotkey>,
+    // 清零并重新开始计时
+    #[serde(default)]
+    pub restart: Option<Hotkey>,
+    #[serde(default)]
+    pub thresholds: Vec<TimerThreshold>,
+    // 设置后作为倒计时使用，`next_preset` 键在这些预设间循环切换
+    #[serde(default)]
+    pub presets: Vec<String>,
+    #[serde(default)]
+    pub next_preset: Option<Hotkey>,
+    // 显示到十分之一秒
+    #[serde(default)]
+    pub show_tenths: bool,
+    // 分段记录在 `reset` 后保留，只有 `clear_laps` 键会清除
+    #[serde(default)]
+    pub lap: Option<Hotkey>,
+    #[serde(default)]
+    pub clear_laps: Option<Hotkey>,
+    #[serde(default)]
+    pub lap_scroll_up: Option<Hotkey>,
+    #[serde(default)]
+    pub lap_scroll_down: Option<Hotkey>,
+    #[serde(default = "default_visible_laps")]
+    pub visible_laps: usize,
+}
+
+impl Default for TimerConfig {
+    fn default() -> Self {
+        TimerConfig {
+            id: String::new(),
+            name: String::new(),
+            font_size: default_timer_font_size(),
+            start: None,
+            stop: None,
+            reset: None,
+            restart: None,
+            thresholds: vec![],
+            presets: vec![],
+            next_preset: None,
+            show_tenths: false,
+            lap: None,
+            clear_laps: None,
+            lap_scroll_up: None,
+            lap_scroll_down: None,
+            visible_laps: default_visible_laps(),
+        }
+    }
+}
+
+#[derive(Serialize, Deserialize, Debug, Clone)]
+pub struct CountdownPreset {
+    pub id: String,
+    pub name: String,
+    pub seconds: u64,
+    // 倒计时的阈值是剩余秒数
+    #[serde(default)]
+    pub thresholds: Vec<TimerThreshold>,
+}
+
+fn default_timer_font_size() -> u32 {
+    10
+}
+
+fn default_visible_laps() -> usize {
+    3
+}
+
+fn default_idle_frame_rate() -> u32 {
+    10
+}
+
+fn default_auto_scroll_seconds() -> u64 {
+    8
+}
+
+// 旧版配置文件没有 `timers` 时使用的计时器
+fn default_timers() -> Vec<TimerConfig> {
+    vec![
+        TimerConfig {
+            id: "main".to_string(),
+            name: String::new(),
+            font_size: 40,
+            start: Some(rdev::Key::Num1.into()),
+            stop: Some(rdev::Key::Num2.into()),
+            reset: Some(rdev::Key::Num3.into()),
+            ..Default::default()
+        },
+        TimerConfig {
+            id: "smudge".to_string(),
+            name: "圣木".to_string(),
+            restart: Some(rdev::Key::Num4.into()),
+            thresholds: vec![
+                TimerThreshold {
+                    seconds: 60,
+                    label: "恶魔可猎杀".to_string(),
+                },
+                TimerThreshold {
+                    seconds: 90,
+                    label: "除魂魄外可猎杀".to_string(),
+                },
+                TimerThreshold {
+                    seconds: 180,
+                    label: "魂魄可猎杀".to_string(),
+                },
+            ],
+            ..Default::default()
+        },
+    ]
+}
+
+#[derive(Serialize, Deserialize, Debug)]
+pub struct Config {
+    pub version: i32,
+    #[serde(default)]
+    pub bindings: Bindings,
+    #[serde(default = "default_timers")]
+    pub timers: Vec<TimerConfig>,
+    #[serde(default)]
+    pub presets: Vec<CountdownPreset>,
+    // 没有变化时检查窗口事件的频率，计时器走动和按键会立即重绘
+    #[serde(default = "default_idle_frame_rate")]
+    pub idle_frame_rate: u32,
+    // 自动翻页时每页停留的秒数
+    #[serde(default = "default_auto_scroll_seconds")]
+    pub auto_scroll_seconds: u64,
+    pub ghosts: Vec<GhostInformation>,
+}
+
+impl Config {
+    pub fn load<P: AsRef<path::Path>>(path: P) -> Result<Config, Box<dyn error::Error>> {
+        let config: Config = serde_json::from_str(&fs::read_to_string(path)?)?;
+
+        if config.version < CONFIG_VERSION {
+            warn!(
+                "配置文件版本为 {}，缺少证据或速度信息，建议更新到版本 {}",
+                config.version, CONFIG_VERSION
+            );
+        } else if config.version > CONFIG_VERSION {
+            warn!(
+                "配置文件版本 {} 高于程序支持的版本 {}",
+                config.version, CONFIG_VERSION
+            );
+        }
+
+        Ok(config)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    // 随程序发布的配置文件能够解析，并且其中的引用都有效
+    #[test]
+    fn shipped_config_is_valid() {
+        let config = Config::load(concat!(env!("CARGO_MANIFEST_DIR"), "/config.json")).unwrap();
+        assert_eq!(config.version, CONFIG_VERSION);
+
+        // 没有证据数据的条目永远不会被排除
+        for ghost in &config.ghosts {
+            assert!(!ghost.evidence.is_empty(), "{}", ghost.id);
+        }
+
+        for timer in &config.timers {
+            for id in &timer.presets {
+                assert!(
+                    config.presets.iter().any(|preset| &preset.id == id),
+                    "{} {}",
+                    timer.id,
+                    id
+                );
+            }
+        }
+    }
+}
@@ -0,0 +1,367 @@
+// phasutils: 为Steam游戏恐鬼症设计的实用工具
+// Copyright (C) 2024  Chen Siyuan
+//
+// This program is free software: you can redistribute it and/or modify
+// it under the terms of the GNU General Public License as published by
+// the Free Software Foundation, either version 3 of the License, or
+// (at your option) any later version.
+//
+// This program is distributed in the hope that it will be useful,
+// but WITHOUT ANY WARRANTY; without even the implied warranty of
+// MERCHANTABILITY or FITNESS FOR A PARTICULAR PURPOSE.  See the
+// GNU General Public License for more details.
+//
+// You should have received a copy of the GNU General Public License
+// along with this program.  If not, see <https://www.gnu.org/licenses/>.
+
+use crate::config::GhostInformation;
+use crate::evidence::Evidence;
+use crate::speed::SpeedEstimate;
+
+pub const MAX_EVIDENCE_COUNT: usize = 3;
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Default)]
+pub enum EvidenceState {
+    #[default]
+    Unknown,
+    Confirmed,
+    Excluded,
+}
+
+impl EvidenceState {
+    // 未知 → 确认 → 排除 → 未知
+    pub fn next(self) -> EvidenceState {
+        match self {
+            EvidenceState::Unknown => EvidenceState::Confirmed,
+            EvidenceState::Confirmed => EvidenceState::Excluded,
+            EvidenceState::Excluded => EvidenceState::Unknown,
+        }
+    }
+}
+
+#[derive(Debug, Clone)]
+pub struct Investigation {
+    states: [EvidenceState; Evidence::ALL.len()],
+    // 当前难度下鬼魂会留下的证据数量（3、2、1 或 0）
+    evidence_count: usize,
+    // 脚步测速的结果
+    speed: Option<SpeedEstimate>,
+}
+
+impl Default for Investigation {
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
+impl Investigation {
+    pub fn new() -> Investigation {
+        Investigation {
+            states: [EvidenceState::Unknown; Evidence::ALL.len()],
+            evidence_count: MAX_EVIDENCE_COUNT,
+            speed: None,
+        }
+    }
+
+    pub fn state(&self, evidence: Evidence) -> EvidenceState {
+        self.states[evidence as usize]
+    }
+
+    pub fn set_state(&mut self, evidence: Evidence, state: EvidenceState) {
+        self.states[evidence as usize] = state;
+    }
+
+    pub fn cycle_state(&mut self, evidence: Evidence) {
+        self.set_state(evidence, self.state(evidence).next());
+    }
+
+    pub fn evidence_count(&self) -> usize {
+        self.evidence_count
+    }
+
+    pub fn set_evidence_count(&mut self, evidence_count: usize) {
+        self.evidence_count = evidence_count.min(MAX_EVIDENCE_COUNT);
+    }
+
+    // 3 → 2 → 1 → 0 → 3
+    pub fn cycle_evidence_count(&mut self) {
+        self.set_evidence_count(match self.evidence_count {
+            0 => MAX_EVIDENCE_COUNT,
+            evidence_count => evidence_count - 1,
+        });
+    }
+
+    pub fn set_speed(&mut self, speed: Option<SpeedEstimate>) {
+        self.speed = speed;
+    }
+
+    pub fn reset(&mut self) {
+        self.states = [EvidenceState::Unknown; Evidence::ALL.len()];
+        self.speed = None;
+    }
+
+    pub fn is_possible(&self, ghost: &GhostInformation) -> bool {
+        // 没有速度数据的条目不按速度排除
+        if let (Some(speed), Some(speed_profile)) = (&self.speed, &ghost.speed_profile) {
+            if !speed_profile.matches(speed) {
+                return false;
+            }
+        }
+
+        // 没有证据数据的条目（如旧版配置文件）无法排除
+        if ghost.evidence.is_empty() {
+            return true;
+        }
+
+        // 额外的假证据在任何难度下都会出现
+        if ghost
+            .forced_evidence
+            .iter()
+            .filter(|evidence| !ghost.evidence.contains(evidence))
+            .any(|evidence| self.state(*evidence) == EvidenceState::Excluded)
+        {
+            return false;
+        }
+
+        if Evidence::ALL.iter().any(|evidence| {
+            self.state(*evidence) == EvidenceState::Confirmed && !ghost.has_evidence(*evidence)
+        }) {
+            return false;
+        }
+
+        // 证据减少时必出证据仍然会出现，零证据时则不会
+        let mut required = 0;
+        let mut available = 0;
+        for evidence in &ghost.evidence {
+            let state = self.state(*evidence);
+            let forced = self.evidence_count > 0 && ghost.forced_evidence.contains(evidence);
+
+            if state == EvidenceState::Excluded {
+                if forced {
+                    return false;
+                }
+            } else {
+                available += 1;
+                if state == EvidenceState::Confirmed || forced {
+                    required += 1;
+                }
+            }
+        }
+
+        required <= self.evidence_count
+            && available >= self.evidence_count.min(ghost.evidence.len())
+    }
+
+    pub fn candidates(&self, ghosts: &[GhostInformation]) -> Vec<usize> {
+        ghosts
+            .iter()
+            .enumerate()
+            .filter(|(_, ghost)| self.is_possible(ghost))
+            .map(|(index, _)| index)
+            .collect()
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn ghost(id: &str, evidence: &[Evidence], forced_evidence: &[Evidence]) -> GhostInformation {
+        GhostInformation {
+            id: id.to_string(),
+            name: id.to_string(),
+            speed: String::new(),
+            features: String::new(),
+            evidence: evidence.to_vec(),
+            forced_evidence: forced_evidence.to_vec(),
+            speed_profile: None,
+        }
+    }
+
+    fn ghosts() -> Vec<GhostInformation> {
+        use Evidence::*;
+
+        vec![
+            ghost("spirit", &[Emf5, SpiritBox, GhostWriting], &[]),
+            ghost(
+                "hantu",
+                &[Fingerprints, GhostOrbs, FreezingTemperatures],
+                &[FreezingTemperatures],
+            ),
+            ghost("goryo", &[Emf5, Fingerprints, Dots], &[Dots]),
+            ghost(
+                "the mimic",
+                &[SpiritBox, Fingerprints, FreezingTemperatures],
+                &[GhostOrbs],
+            ),
+        ]
+    }
+
+    fn ids(investigation: &Investigation, ghosts: &[GhostInformation]) -> Vec<String> {
+        investigation
+            .candidates(ghosts)
+            .into_iter()
+            .map(|index| ghosts[index].id.clone())
+            .collect()
+    }
+
+    #[test]
+    fn no_evidence_keeps_every_ghost() {
+        let ghosts = ghosts();
+        assert_eq!(Investigation::new().candidates(&ghosts), vec![0, 1, 2, 3]);
+    }
+
+    #[test]
+    fn confirmed_evidence_filters_ghosts() {
+        let ghosts = ghosts();
+        let mut investigation = Investigation::new();
+        investigation.set_state(Evidence::Fingerprints, EvidenceState::Confirmed);
+        assert_eq!(
+            ids(&investigation, &ghosts),
+            ["hantu", "goryo", "the mimic"]
+        );
+
+        investigation.set_state(Evidence::Emf5, EvidenceState::Confirmed);
+        assert_eq!(ids(&investigation, &ghosts), ["goryo"]);
+    }
+
+    #[test]
+    fn excluded_evidence_filters_ghosts() {
+        let ghosts = ghosts();
+        let mut investigation = Investigation::new();
+        investigation.set_state(Evidence::Emf5, EvidenceState::Excluded);
+        assert_eq!(ids(&investigation, &ghosts), ["hantu", "the mimic"]);
+    }
+
+    #[test]
+    fn too_many_confirmed_for_difficulty() {
+        let ghosts = ghosts();
+        let mut investigation = Investigation::new();
+        investigation.set_evidence_count(1);
+        investigation.set_state(Evidence::Emf5, EvidenceState::Confirmed);
+        investigation.set_state(Evidence::SpiritBox, EvidenceState::Confirmed);
+        assert!(ids(&investigation, &ghosts).is_empty());
+    }
+
+    #[test]
+    fn reduced_evidence_allows_excluding_one() {
+        let ghosts = ghosts();
+        let mut investigation = Investigation::new();
+        investigation.set_state(Evidence::SpiritBox, EvidenceState::Excluded);
+        assert!(!ids(&investigation, &ghosts).contains(&"spirit".to_string()));
+
+        investigation.set_evidence_count(2);
+        assert!(ids(&investigation, &ghosts).contains(&"spirit".to_string()));
+    }
+
+    #[test]
+    fn forced_evidence_must_appear() {
+        let ghosts = ghosts();
+        let mut investigation = Investigation::new();
+        investigation.set_evidence_count(1);
+        investigation.set_state(Evidence::Fingerprints, EvidenceState::Confirmed);
+        // 一证据时寒魔只会给出冰点，御灵只会给出 DOTS
+        assert_eq!(ids(&investigation, &ghosts), ["the mimic"]);
+
+        investigation.set_evidence_count(0);
+        investigation.reset();
+        investigation.set_state(Evidence::FreezingTemperatures, EvidenceState::Excluded);
+        assert_eq!(
+            ids(&investigation, &ghosts),
+            ["spirit", "hantu", "goryo", "the mimic"]
+        );
+    }
+
+    #[test]
+    fn mimic_always_shows_orbs() {
+        let ghosts = ghosts();
+        let mut investigation = Investigation::new();
+        investigation.set_evidence_count(0);
+        investigation.set_state(Evidence::GhostOrbs, EvidenceState::Confirmed);
+        assert_eq!(ids(&investigation, &ghosts), ["the mimic"]);
+
+        investigation.set_state(Evidence::GhostOrbs, EvidenceState::Excluded);
+        assert!(!ids(&investigation, &ghosts).contains(&"the mimic".to_string()));
+    }
+
+    #[test]
+    fn cycle_state_and_evidence_count() {
+        let mut investigation = Investigation::new();
+        investigation.cycle_state(Evidence::Dots);
+        assert_eq!(
+            investigation.state(Evidence::Dots),
+            EvidenceState::Confirmed
+        );
+        investigation.cycle_state(Evidence::Dots);
+        assert_eq!(investigation.state(Evidence::Dots), EvidenceState::Excluded);
+        investigation.cycle_state(Evidence::Dots);
+        assert_eq!(investigation.state(Evidence::Dots), EvidenceState::Unknown);
+
+        let counts: Vec<usize> = (0..4)
+            .map(|_| {
+                investigation.cycle_evidence_count();
+                investigation.evidence_count()
+            })
+            .collect();
+        assert_eq!(counts, [2, 1, 0, 3]);
+    }
+
+    #[test]
+    fn measured_speed_filters_ghosts() {
+        use crate::speed::{SpeedClass, SpeedProfile, SpeedRule};
+
+        let mut ghosts = ghosts();
+        ghosts[0].speed_profile = Some(SpeedProfile {
+            base: 1.7,
+            max: 2.8,
+            min: None,
+            line_of_sight: true,
+            rule: None,
+        });
+        ghosts[1].speed_profile = Some(SpeedProfile {
+            base: 1.4,
+            max: 2.7,
+            min: None,
+            line_of_sight: false,
+            rule: Some(SpeedRule::Hantu),
+        });
+        ghosts[2].speed_profile = Some(SpeedProfile {
+            base: 1.7,
+            max: 1.7,
+            min: None,
+            line_of_sight: false,
+            rule: None,
+        });
+
+        let mut investigation = Investigation::new();
+        investigation.set_speed(Some(SpeedEstimate {
+            steps_per_second: 1.5,
+            meters_per_second: 1.3,
+            class: SpeedClass::Slow,
+            accelerating: false,
+        }));
+        assert_eq!(ids(&investigation, &ghosts), ["hantu", "the mimic"]);
+
+        investigation.set_speed(Some(SpeedEstimate {
+            steps_per_second: 3.0,
+            meters_per_second: 2.5,
+            class: SpeedClass::Fast,
+            accelerating: true,
+        }));
+        assert_eq!(
+            ids(&investigation, &ghosts),
+            ["spirit", "hantu", "the mimic"]
+        );
+
+        investigation.reset();
+        assert_eq!(investigation.candidates(&ghosts), vec![0, 1, 2, 3]);
+    }
+
+    #[test]
+    fn ghost_without_evidence_data_is_kept() {
+        let ghosts = vec![ghost("None", &[], &[])];
+        let mut investigation = Investigation::new();
+        investigation.set_state(Evidence::Dots, EvidenceState::Confirmed);
+        assert_eq!(investigation.candidates(&ghosts), vec![0]);
+    }
+}
@@ -0,0 +1,54 @@
+// phasutils: 为Steam游戏恐鬼症设计的实用工具
+// Copyright (C) 2024  Chen Siyuan
+//
+// This program is free software: you can redistribute it and/or modify
+// it under the terms of the GNU General Public License as published by
+// the Free Software Foundation, either version 3 of the License, or
+// (at your option) any later version.
+//
+// This program is distributed in the hope that it will be useful,
+// but WITHOUT ANY WARRANTY; without even the implied warranty of
+// MERCHANTABILITY or FITNESS FOR A PARTICULAR PURPOSE.  See the
+// GNU General Public License for more details.
+//
+// You should have received a copy of the GNU General Public License
+// along with this program.  If not, see <https://www.gnu.org/licenses/>.
+
+use serde::{Deserialize, Serialize};
+
+/// 游戏中的七种证据
+#[derive(Serialize, Deserialize, Debug, Clone, Copy, PartialEq, Eq, Hash)]
+#[serde(rename_all = "snake_case")]
+pub enum Evidence {
+    Emf5,
+    SpiritBox,
+    Fingerprints,
+    GhostOrbs,
+    GhostWriting,
+    FreezingTemperatures,
+    Dots,
+}
+
+impl Evidence {
+    pub const ALL: [Evidence; 7] = [
+        Evidence::Emf5,
+        Evidence::SpiritBox,
+        Evidence::Fingerprints,
+        Evidence::GhostOrbs,
+        Evidence::GhostWriting,
+        Evidence::FreezingTemperatures,
+        Evidence::Dots,
+    ];
+
+    pub fn name(&self) -> &'static str {
+        match self {
+            Evidence::Emf5 => "EMF5",
+            Evidence::SpiritBox => "通灵盒",
+            Evidence::Fingerprints => "指纹",
+            Evidence::GhostOrbs => "灵球",
+            Evidence::GhostWriting => "鬼书",
+            Evidence::FreezingTemperatures => "冰点",
+            Evidence::Dots => "DOTS",
+        }
+    }
+}
@@ -0,0 +1,107 @@
+// phasutils: 为Steam游戏恐鬼症设计的实用工具
+// Copyright (C) 2024  Chen Siyuan
+//
+// This program is free software: you can redistribute it and/or modify
+// it under the terms of the GNU General Public License as published by
+// the Free Software Foundation, either version 3 of the License, or
+// (at your option) any later version.
+//
+// This program is distributed in the hope that it will be useful,
+// but WITHOUT ANY WARRANTY; without even the implied warranty of
+// MERCHANTABILITY or FITNESS FOR A PARTICULAR PURPOSE.  See the
+// GNU General Public License for more details.
+//
+// You should have received a copy of the GNU General Public License
+// along with this program.  If not, see <https://www.gnu.org/licenses/>.
+
+use std::ops::Range;
+
+use log::warn;
+
+// 按顺序检查每个字体是否包含某个字形，用于选择后备字体
+pub struct Coverage {
+    faces: Vec<Option<ttf_parser::Face<'static>>>,
+}
+
+impl Coverage {
+    pub fn new(data: &[&'static [u8]]) -> Coverage {
+        let faces = data
+            .iter()
+            .enumerate()
+            .map(|(index, data)| {
+                ttf_parser::Face::parse(data, 0)
+                    .map_err(|err| warn!("无法读取第 {} 个字体的字形表: {}", index + 1, err))
+                    .ok()
+            })
+            .collect();
+
+        Coverage { faces }
+    }
+
+    // 所有字体都没有这个字形时使用第一个字体，显示为方框
+    pub fn font_for(&self, c: char) -> usize {
+        self.faces
+            .iter()
+            .position(|face| {
+                face.as_ref()
+                    .is_some_and(|face| face.glyph_index(c).is_some())
+            })
+            .unwrap_or(0)
+    }
+}
+
+// 把一行文字拆成使用同一字体的几段，空白跟随前一段，避免无谓地拆开
+pub fn runs<F: FnMut(char) -> usize>(line: &str, mut font_for: F) -> Vec<(usize, Range<usize>)> {
+    let mut runs: Vec<(usize, Range<usize>)> = vec![];
+    for (index, c) in line.char_indices() {
+        let end = index + c.len_utf8();
+        match runs.last_mut() {
+            Some((_, range)) if c.is_whitespace() => range.end = end,
+            Some((font, range)) if *font == font_for(c) => range.end = end,
+            _ => runs.push((font_for(c), index..end)),
+        }
+    }
+
+    runs
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    // 假设第一个字体只有 ASCII，第二个字体只有其他字符
+    fn font_for(c: char) -> usize {
+        if c.is_ascii() {
+            0
+        } else {
+            1
+        }
+    }
+
+    fn split(line: &str) -> Vec<(usize, &str)> {
+        runs(line, font_for)
+            .into_iter()
+            .map(|(font, range)| (font, &line[range]))
+            .collect()
+    }
+
+    #[test]
+    fn single_font_is_one_run() {
+        assert_eq!(split("EMF 5"), [(0, "EMF 5")]);
+        assert!(split("").is_empty());
+    }
+
+    #[test]
+    fn switch_font_for_missing_glyphs() {
+        assert_eq!(split("EMF5检测器"), [(0, "EMF5"), (1, "检测器")]);
+        assert_eq!(
+            split("[1/24] 魂魄 (spirit)"),
+            [(0, "[1/24] "), (1, "魂魄 "), (0, "(spirit)")]
+        );
+    }
+
+    #[test]
+    fn leading_whitespace_starts_a_run() {
+        assert_eq!(split(" 鬼"), [(0, " "), (1, "鬼")]);
+    }
+}
@@ -0,0 +1,795 @@
+// phasutils: 为Steam游戏恐鬼症设计的实用工具
+// Copyright (C) 2024  Chen Siyuan
+//
+// This program is free software: you can redistribute it and/or modify
+// it under the terms of the GNU General Public License as published by
+// the Free Software Foundation, either version 3 of the License, or
+// (at your option) any later version.
+//
+// This program is distributed in the hope that it will be useful,
+// but WITHOUT ANY WARRANTY; without even the implied warranty of
+// MERCHANTABILITY or FITNESS FOR A PARTICULAR PURPOSE.  See the
+// GNU General Public License for more details.
+//
+// You should have received a copy of the GNU General Public License
+// along with this program.  If not, see <https://www.gnu.org/licenses/>.
+
+use std::collections::HashMap;
+use std::{fmt, str, time};
+
+use serde::{de, Deserialize, Deserializer, Serialize, Serializer};
+
+use crate::evidence::Evidence;
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Default)]
+pub struct Modifiers {
+    pub ctrl: bool,
+    pub alt: bool,
+    pub shift: bool,
+    pub meta: bool,
+}
+
+// 根据 `KeyPress` 和 `KeyRelease` 记录按住的修饰键
+#[derive(Debug, Default)]
+pub struct ModifierState {
+    held: Vec<rdev::Key>,
+}
+
+impl ModifierState {
+    pub fn new() -> ModifierState {
+        ModifierState { held: vec![] }
+    }
+
+    pub fn press(&mut self, key: rdev::Key) {
+        if is_modifier(key) && !self.held.contains(&key) {
+            self.held.push(key);
+        }
+    }
+
+    pub fn release(&mut self, key: rdev::Key) {
+        self.held.retain(|held| *held != key);
+    }
+
+    pub fn modifiers(&self) -> Modifiers {
+        let mut modifiers = Modifiers::default();
+        for key in &self.held {
+            match key {
+                rdev::Key::ControlLeft | rdev::Key::ControlRight => modifiers.ctrl = true,
+                rdev::Key::Alt | rdev::Key::AltGr => modifiers.alt = true,
+                rdev::Key::ShiftLeft | rdev::Key::ShiftRight => modifiers.shift = true,
+                rdev::Key::MetaLeft | rdev::Key::MetaRight => modifiers.meta = true,
+                _ => {}
+            }
+        }
+
+        modifiers
+    }
+}
+
+fn is_modifier(key: rdev::Key) -> bool {
+    matches!(
+        key,
+        rdev::Key::ControlLeft
+            | rdev::Key::ControlRight
+            | rdev::Key::Alt
+            | rdev::Key::AltGr
+            | rdev::Key::ShiftLeft
+            | rdev::Key::ShiftRight
+            | rdev::Key::MetaLeft
+            | rdev::Key::MetaRight
+    )
+}
+
+// 鼠标侧键统一使用 X11 的编号，其他平台的编号在 `source` 中换算
+pub const MOUSE_BACK: rdev::Button = rdev::Button::Unknown(8);
+pub const MOUSE_FORWARD: rdev::Button = rdev::Button::Unknown(9);
+
+// 触发热键的输入：键盘按键、鼠标按键或手柄按键
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub enum Trigger {
+    Key(rdev::Key),
+    Mouse(rdev::Button),
+    Gamepad(gilrs::Button),
+}
+
+impl Trigger {
+    pub fn name(&self) -> String {
+        match self {
+            Trigger::Key(key) => key_name(*key),
+            Trigger::Mouse(rdev::Button::Left) => "鼠标左键".to_string(),
+            Trigger::Mouse(rdev::Button::Right) => "鼠标右键".to_string(),
+            Trigger::Mouse(rdev::Button::Middle) => "鼠标中键".to_string(),
+            Trigger::Mouse(MOUSE_BACK) => "鼠标后退键".to_string(),
+            Trigger::Mouse(MOUSE_FORWARD) => "鼠标前进键".to_string(),
+            Trigger::Mouse(rdev::Button::Unknown(code)) => format!("鼠标键{}", code),
+            Trigger::Gamepad(button) => format!("手柄{:?}", button),
+        }
+    }
+}
+
+impl fmt::Display for Trigger {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            Trigger::Key(key) => write!(f, "{:?}", key),
+            Trigger::Mouse(rdev::Button::Left) => write!(f, "MouseLeft"),
+            Trigger::Mouse(rdev::Button::Right) => write!(f, "MouseRight"),
+            Trigger::Mouse(rdev::Button::Middle) => write!(f, "MouseMiddle"),
+            Trigger::Mouse(MOUSE_BACK) => write!(f, "MouseBack"),
+            Trigger::Mouse(MOUSE_FORWARD) => write!(f, "MouseForward"),
+            Trigger::Mouse(rdev::Button::Unknown(code)) => write!(f, "Mouse{}", code),
+            Trigger::Gamepad(button) => write!(f, "Pad{:?}", button),
+        }
+    }
+}
+
+impl str::FromStr for Trigger {
+    type Err = String;
+
+    // 鼠标写作 "MouseLeft"、"MouseRight"、"MouseMiddle"、"MouseBack"、"MouseForward"
+    // 或其他按键的编号 "Mouse10"，手柄写作 "Pad" 加 gilrs 的按键名，如 "PadSouth"
+    fn from_str(text: &str) -> Result<Self, Self::Err> {
+        if let Some(button) = text.strip_prefix("Mouse") {
+            return match button {
+                "Left" => Ok(Trigger::Mouse(rdev::Button::Left)),
+                "Right" => Ok(Trigger::Mouse(rdev::Button::Right)),
+                "Middle" => Ok(Trigger::Mouse(rdev::Button::Middle)),
+                "Back" => Ok(Trigger::Mouse(MOUSE_BACK)),
+                "Forward" => Ok(Trigger::Mouse(MOUSE_FORWARD)),
+                code => code
+                    .parse()
+                    .map(|code| Trigger::Mouse(rdev::Button::Unknown(code)))
+                    .map_err(|_| format!("未知的鼠标按键 {}", text)),
+            };
+        }
+
+        if let Some(button) = text.strip_prefix("Pad") {
+            return serde_json::from_value(serde_json::Value::String(button.to_string()))
+                .map(Trigger::Gamepad)
+                .map_err(|_| format!("未知的手柄按键 {}", text));
+        }
+
+        serde_json::from_value(serde_json::Value::String(text.to_string()))
+            .map(Trigger::Key)
+            .map_err(|_| format!("未知的按键 {}", text))
+    }
+}
+
+// 配置文件中写作 "Ctrl+Alt+Num1"，修饰键必须完全一致才会触发
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub struct Hotkey {
+    pub trigger: Trigger,
+    pub modifiers: Modifiers,
+}
+
+impl Hotkey {
+    pub fn new(trigger: Trigger, modifiers: Modifiers) -> Hotkey {
+        Hotkey { trigger, modifiers }
+    }
+
+    // 在提示中显示的名称
+    pub fn name(&self) -> String {
+        let mut name = String::new();
+        for (held, modifier) in self.modifier_names() {
+            if held {
+                name += modifier;
+                name += "+";
+            }
+        }
+
+        name + &self.trigger.name()
+    }
+
+    fn modifier_names(&self) -> [(bool, &'static str); 4] {
+        [
+            (self.modifiers.ctrl, "Ctrl"),
+            (self.modifiers.alt, "Alt"),
+            (self.modifiers.shift, "Shift"),
+            (self.modifiers.meta, "Meta"),
+        ]
+    }
+}
+
+impl From<rdev::Key> for Hotkey {
+    fn from(key: rdev::Key) -> Self {
+        Hotkey::new(Trigger::Key(key), Modifiers::default())
+    }
+}
+
+impl fmt::Display for Hotkey {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        for (held, modifier) in self.modifier_names() {
+            if held {
+                write!(f, "{}+", modifier)?;
+            }
+        }
+
+        write!(f, "{}", self.trigger)
+    }
+}
+
+impl str::FromStr for Hotkey {
+    type Err = String;
+
+    fn from_str(text: &str) -> Result<Self, Self::Err> {
+        let mut parts: Vec<&str> = text.split('+').map(str::trim).collect();
+        let trigger: Trigger = parts.pop().unwrap_or_default().parse()?;
+
+        let mut modifiers = Modifiers::default();
+        for part in parts {
+            match part.to_ascii_lowercase().as_str() {
+                "ctrl" | "control" => modifiers.ctrl = true,
+                "alt" => modifiers.alt = true,
+                "shift" => modifiers.shift = true,
+                "meta" | "win" | "super" => modifiers.meta = true,
+                _ => return Err(format!("未知的修饰键 {}", part)),
+            }
+        }
+
+        Ok(Hotkey::new(trigger, modifiers))
+    }
+}
+
+impl Serialize for Hotkey {
+    fn serialize<S: Serializer>(&self, serializer: S) -> Result<S::Ok, S::Error> {
+        serializer.serialize_str(&self.to_string())
+    }
+}
+
+impl<'de> Deserialize<'de> for Hotkey {
+    fn deserialize<D: Deserializer<'de>>(deserializer: D) -> Result<Self, D::Error> {
+        String::deserialize(deserializer)?
+            .parse()
+            .map_err(de::Error::custom)
+    }
+}
+
+// 防止误触的确认方式
+#[derive(Serialize, Deserialize, Debug, Clone, Copy, PartialEq, Eq)]
+#[serde(rename_all = "snake_case", tag = "type")]
+pub enum ConfirmMode {
+    Press,
+    Hold { millis: u64 },
+    DoublePress { millis: u64 },
+}
+
+impl Default for ConfirmMode {
+    fn default() -> Self {
+        ConfirmMode::Hold { millis: 1000 }
+    }
+}
+
+pub struct Confirmation {
+    mode: ConfirmMode,
+    pressed_at: Option<time::Instant>,
+    // 按键还没有松开，按住时系统产生的重复按键不算再次按下
+    held: bool,
+}
+
+impl Confirmation {
+    pub fn new(mode: ConfirmMode) -> Confirmation {
+        Confirmation {
+            mode,
+            pressed_at: None,
+            held: false,
+        }
+    }
+
+    // 立即确认时返回 true；按住确认需要调用 `poll`
+    pub fn press(&mut self, now: time::Instant) -> bool {
+        match self.mode {
+            ConfirmMode::Press => true,
+            // 忽略按住时系统产生的重复按键
+            ConfirmMode::Hold { .. } => {
+                self.pressed_at.get_or_insert(now);
+                false
+            }
+            // 松开之后的下一次按下才算第二次
+            ConfirmMode::DoublePress { .. } => {
+                if self.held {
+                    return false;
+                }
+                self.held = true;
+
+                if self.remaining(now).is_some() {
+                    self.pressed_at = None;
+                    true
+                } else {
+                    self.pressed_at = Some(now);
+                    false
+                }
+            }
+        }
+    }
+
+    pub fn release(&mut self) {
+        self.held = false;
+        if let ConfirmMode::Hold { .. } = self.mode {
+            self.pressed_at = None;
+        }
+    }
+
+    pub fn poll(&self, now: time::Instant) -> bool {
+        matches!(self.mode, ConfirmMode::Hold { .. })
+            && self.pressed_at.is_some()
+            && self.remaining(now).is_none()
+    }
+
+    // 按住还需的时间，或等待第二次按下的剩余时间
+    pub fn remaining(&self, now: time::Instant) -> Option<time::Duration> {
+        let millis = match self.mode {
+            ConfirmMode::Press => return None,
+            ConfirmMode::Hold { millis } | ConfirmMode::DoublePress { millis } => millis,
+        };
+        let elapsed = now.duration_since(self.pressed_at?);
+        time::Duration::from_millis(millis)
+            .checked_sub(elapsed)
+            .filter(|remaining| !remaining.is_zero())
+    }
+
+    pub fn mode(&self) -> ConfirmMode {
+        self.mode
+    }
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum Action {
+    ToggleArmed,
+    Quit,
+    ToggleHidden,
+    ToggleCompact,
+    PreviousGhost,
+    NextGhost,
+    TapSpeed,
+    CycleEvidenceCount,
+    ResetInvestigation,
+    CycleEvidence(Evidence),
+    PreviousFeaturesPage,
+    NextFeaturesPage,
+    ToggleAutoScroll,
+    Drag,
+}
+
+// 计时器的按键在 `timers` 中单独设置
+#[derive(Serialize, Deserialize, Debug, Clone)]
+#[serde(default)]
+pub struct Bindings {
+    // 启用或停用其他所有热键
+    pub arm: Option<Hotkey>,
+    pub quit: Option<Hotkey>,
+    pub quit_confirm: ConfirmMode,
+    // 隐藏或重新显示悬浮窗，计时器在隐藏时继续运行
+    pub toggle_hidden: Option<Hotkey>,
+    // 在完整模式和只显示计时器、候选数量的精简模式之间切换
+    pub compact: Option<Hotkey>,
+    pub previous_ghost: Option<Hotkey>,
+    pub next_ghost: Option<Hotkey>,
+    pub tap_speed: Option<Hotkey>,
+    pub cycle_evidence_count: Option<Hotkey>,
+    pub reset_investigation: Option<Hotkey>,
+    pub evidence: HashMap<Evidence, Hotkey>,
+    // 鬼魂特性太长时翻页
+    pub previous_features_page: Option<Hotkey>,
+    pub next_features_page: Option<Hotkey>,
+    pub auto_scroll: Option<Hotkey>,
+    // 按住时悬浮窗跟随鼠标移动，松开后保存位置
+    pub drag: Option<Hotkey>,
+}
+
+impl Default for Bindings {
+    fn default() -> Self {
+        Bindings {
+            arm: Some(rdev::Key::ScrollLock.into()),
+            quit: Some(rdev::Key::Num0.into()),
+            quit_confirm: ConfirmMode::default(),
+            toggle_hidden: None,
+            compact: None,
+            previous_ghost: Some(rdev::Key::KeyZ.into()),
+            next_ghost: Some(rdev::Key::KeyX.into()),
+            tap_speed: Some(rdev::Key::BackQuote.into()),
+            cycle_evidence_count: Some(rdev::Key::F8.into()),
+            reset_investigation: Some(rdev::Key::F9.into()),
+            evidence: Evidence::ALL
+                .into_iter()
+                .zip([
+                    rdev::Key::F1,
+                    rdev::Key::F2,
+                    rdev::Key::F3,
+                    rdev::Key::F4,
+                    rdev::Key::F5,
+                    rdev::Key::F6,
+                    rdev::Key::F7,
+                ])
+                .map(|(evidence, key)| (evidence, key.into()))
+                .collect(),
+            previous_features_page: Some(rdev::Key::PageUp.into()),
+            next_features_page: Some(rdev::Key::PageDown.into()),
+            auto_scroll: None,
+            drag: None,
+        }
+    }
+}
+
+impl Bindings {
+    pub fn action(&self, hotkey: Hotkey) -> Option<Action> {
+        let key = Some(hotkey);
+        if key == self.arm {
+            Some(Action::ToggleArmed)
+        } else if key == self.quit {
+            Some(Action::Quit)
+        } else if key == self.toggle_hidden {
+            Some(Action::ToggleHidden)
+        } else if key == self.compact {
+            Some(Action::ToggleCompact)
+        } else if key == self.previous_ghost {
+            Some(Action::PreviousGhost)
+        } else if key == self.next_ghost {
+            Some(Action::NextGhost)
+        } else if key == self.tap_speed {
+            Some(Action::TapSpeed)
+        } else if key == self.cycle_evidence_count {
+            Some(Action::CycleEvidenceCount)
+        } else if key == self.reset_investigation {
+            Some(Action::ResetInvestigation)
+        } else if key == self.previous_features_page {
+            Some(Action::PreviousFeaturesPage)
+        } else if key == self.next_features_page {
+            Some(Action::NextFeaturesPage)
+        } else if key == self.auto_scroll {
+            Some(Action::ToggleAutoScroll)
+        } else if key == self.drag {
+            Some(Action::Drag)
+        } else {
+            Evidence::ALL
+                .into_iter()
+                .find(|evidence| self.evidence.get(evidence).copied() == key)
+                .map(Action::CycleEvidence)
+        }
+    }
+
+    pub fn tips(&self) -> Vec<String> {
+        let mut tips = vec![];
+
+        if let Some(key) = self.arm {
+            tips.push(format!("[{}] 键启用/停用热键", key.name()));
+        }
+        if let Some(key) = self.quit {
+            match self.quit_confirm {
+                ConfirmMode::Press => tips.push(format!("[{}] 键退出", key.name())),
+                ConfirmMode::Hold { millis } => tips.push(format!(
+                    "按住 [{}] 键 {:.1} 秒退出",
+                    key.name(),
+                    millis as f32 / 1000.0
+                )),
+                ConfirmMode::DoublePress { .. } => {
+                    tips.push(format!("连按两次 [{}] 键退出", key.name()))
+                }
+            }
+        }
+        if let Some(key) = self.toggle_hidden {
+            tips.push(format!("[{}] 键隐藏/显示悬浮窗", key.name()));
+        }
+        if let Some(key) = self.compact {
+            tips.push(format!("[{}] 键切换完整/精简模式", key.name()));
+        }
+        if let Some(key) = self.drag {
+            tips.push(format!("按住 [{}] 键拖动悬浮窗", key.name()));
+        }
+        match (self.previous_ghost, self.next_ghost) {
+            (Some(previous), Some(next)) => tips.push(format!(
+                "[{}/{}] 键切换到上/下一个鬼魂特性",
+                previous.name(),
+                next.name()
+            )),
+            (Some(previous), None) => {
+                tips.push(format!("[{}] 键切换到上一个鬼魂特性", previous.name()))
+            }
+            (None, Some(next)) => tips.push(format!("[{}] 键切换到下一个鬼魂特性", next.name())),
+            (None, None) => {}
+        }
+        match (self.previous_features_page, self.next_features_page) {
+            (Some(previous), Some(next)) => tips.push(format!(
+                "[{}/{}] 键翻看鬼魂特性",
+                previous.name(),
+                next.name()
+            )),
+            (Some(key), None) | (None, Some(key)) => {
+                tips.push(format!("[{}] 键翻看鬼魂特性", key.name()))
+            }
+            (None, None) => {}
+        }
+        if let Some(key) = self.auto_scroll {
+            tips.push(format!("[{}] 键自动翻页", key.name()));
+        }
+        if let Some(key) = self.tap_speed {
+            tips.push(format!("[{}] 键跟随脚步声点击测速", key.name()));
+        }
+
+        let evidence_keys: Vec<String> = Evidence::ALL
+            .iter()
+            .filter_map(|evidence| self.evidence.get(evidence))
+            .map(|key| key.name())
+            .collect();
+        if !evidence_keys.is_empty() {
+            tips.push(format!(
+                "[{}] 键切换证据 未知/确认/排除",
+                evidence_keys.join("/")
+            ));
+        }
+
+        if let Some(key) = self.cycle_evidence_count {
+            tips.push(format!("[{}] 键切换证据数量", key.name()));
+        }
+        if let Some(key) = self.reset_investigation {
+            tips.push(format!("[{}] 键清空证据和测速", key.name()));
+        }
+
+        tips
+    }
+}
+
+// 按键在提示中显示的名称
+pub fn key_name(key: rdev::Key) -> String {
+    match key {
+        rdev::Key::BackQuote => "`".to_string(),
+        rdev::Key::Minus => "-".to_string(),
+        rdev::Key::Equal => "=".to_string(),
+        rdev::Key::LeftBracket => "[".to_string(),
+        rdev::Key::RightBracket => "]".to_string(),
+        rdev::Key::SemiColon => ";".to_string(),
+        rdev::Key::Quote => "'".to_string(),
+        rdev::Key::BackSlash => "\\".to_string(),
+        rdev::Key::Comma => ",".to_string(),
+        rdev::Key::Dot => ".".to_string(),
+        rdev::Key::Slash => "/".to_string(),
+        _ => {
+            let name = format!("{:?}", key);
+            let is_digit = |suffix: &&str| suffix.chars().all(|char| char.is_ascii_digit());
+            if let Some(digit) = name.strip_prefix("Num").filter(is_digit) {
+                digit.to_string()
+            } else if let Some(digit) = name.strip_prefix("Kp").filter(is_digit) {
+                format!("小键盘{}", digit)
+            } else {
+                name.trim_start_matches("Key").to_string()
+            }
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn modifiers(ctrl: bool, alt: bool, shift: bool, meta: bool) -> Modifiers {
+        Modifiers {
+            ctrl,
+            alt,
+            shift,
+            meta,
+        }
+    }
+
+    #[test]
+    fn parse_hotkey() {
+        assert_eq!(
+            "Ctrl+Alt+Num1".parse::<Hotkey>(),
+            Ok(Hotkey::new(
+                Trigger::Key(rdev::Key::Num1),
+                modifiers(true, true, false, false)
+            ))
+        );
+        assert_eq!("F8".parse::<Hotkey>(), Ok(Hotkey::from(rdev::Key::F8)));
+        // 修饰键不区分大小写，可以有空格和别名
+        assert_eq!(
+            " control + SHIFT + win + KeyA ".parse::<Hotkey>(),
+            Ok(Hotkey::new(
+                Trigger::Key(rdev::Key::KeyA),
+                modifiers(true, false, true, true)
+            ))
+        );
+    }
+
+    #[test]
+    fn hotkey_round_trip() {
+        for text in [
+            "Num1",
+            "Ctrl+Alt+Num1",
+            "Shift+F12",
+            "Ctrl+Alt+Shift+Meta+KeyC",
+            "Alt+BackQuote",
+        ] {
+            let hotkey: Hotkey = text.parse().unwrap();
+            assert_eq!(hotkey.to_string(), text);
+
+            let json = serde_json::to_string(&hotkey).unwrap();
+            assert_eq!(json, format!("\"{}\"", text));
+            assert_eq!(serde_json::from_str::<Hotkey>(&json).unwrap(), hotkey);
+        }
+
+        // 别名按固定的顺序和名称输出
+        let hotkey: Hotkey = "super+shift+control+F1".parse().unwrap();
+        assert_eq!(hotkey.to_string(), "Ctrl+Shift+Meta+F1");
+    }
+
+    #[test]
+    fn invalid_hotkeys() {
+        assert_eq!(
+            "Hyper+Num1".parse::<Hotkey>(),
+            Err("未知的修饰键 Hyper".to_string())
+        );
+        assert_eq!("".parse::<Hotkey>(), Err("未知的按键 ".to_string()));
+        assert_eq!("Ctrl+".parse::<Hotkey>(), Err("未知的按键 ".to_string()));
+        assert_eq!(
+            "Ctrl+Banana".parse::<Hotkey>(),
+            Err("未知的按键 Banana".to_string())
+        );
+        assert!(serde_json::from_str::<Hotkey>("\"Alt+\"").is_err());
+    }
+
+    #[test]
+    fn names_in_tips() {
+        for (key, name) in [
+            (rdev::Key::Num1, "1"),
+            (rdev::Key::Kp5, "小键盘5"),
+            (rdev::Key::KpMinus, "KpMinus"),
+            (rdev::Key::KeyA, "A"),
+            (rdev::Key::F10, "F10"),
+            (rdev::Key::BackQuote, "`"),
+            (rdev::Key::LeftBracket, "["),
+            (rdev::Key::ScrollLock, "ScrollLock"),
+        ] {
+            assert_eq!(key_name(key), name);
+        }
+
+        let hotkey: Hotkey = "Ctrl+Alt+Num1".parse().unwrap();
+        assert_eq!(hotkey.name(), "Ctrl+Alt+1");
+    }
+
+    fn millis(millis: u64) -> time::Duration {
+        time::Duration::from_millis(millis)
+    }
+
+    #[test]
+    fn press_confirms_immediately() {
+        let mut confirmation = Confirmation::new(ConfirmMode::Press);
+        let now = time::Instant::now();
+        assert!(confirmation.press(now));
+        assert!(!confirmation.poll(now));
+        assert_eq!(confirmation.remaining(now), None);
+    }
+
+    #[test]
+    fn hold_until_confirmed() {
+        let mut confirmation = Confirmation::new(ConfirmMode::Hold { millis: 1000 });
+        let start = time::Instant::now();
+        assert!(!confirmation.press(start));
+        assert!(!confirmation.poll(start + millis(500)));
+        assert_eq!(
+            confirmation.remaining(start + millis(400)),
+            Some(millis(600))
+        );
+
+        // 按住时的重复按键不会重新计时
+        assert!(!confirmation.press(start + millis(500)));
+        assert!(confirmation.poll(start + millis(1000)));
+    }
+
+    #[test]
+    fn hold_released_early() {
+        let mut confirmation = Confirmation::new(ConfirmMode::Hold { millis: 1000 });
+        let start = time::Instant::now();
+        confirmation.press(start);
+        confirmation.release();
+        assert!(!confirmation.poll(start + millis(2000)));
+        assert_eq!(confirmation.remaining(start + millis(500)), None);
+    }
+
+    #[test]
+    fn double_press_within_window() {
+        let mut confirmation = Confirmation::new(ConfirmMode::DoublePress { millis: 500 });
+        let start = time::Instant::now();
+        assert!(!confirmation.press(start));
+        assert_eq!(
+            confirmation.remaining(start + millis(200)),
+            Some(millis(300))
+        );
+        confirmation.release();
+        assert!(confirmation.press(start + millis(300)));
+        assert!(!confirmation.poll(start + millis(300)));
+
+        // 确认后重新开始
+        confirmation.release();
+        assert!(!confirmation.press(start + millis(400)));
+        confirmation.release();
+        assert!(confirmation.press(start + millis(800)));
+    }
+
+    #[test]
+    fn double_press_window_expires() {
+        let mut confirmation = Confirmation::new(ConfirmMode::DoublePress { millis: 500 });
+        let start = time::Instant::now();
+        confirmation.press(start);
+        confirmation.release();
+        assert_eq!(confirmation.remaining(start + millis(500)), None);
+        // 超时后的按键重新等待第二次按下
+        assert!(!confirmation.press(start + millis(600)));
+        confirmation.release();
+        assert!(confirmation.press(start + millis(900)));
+    }
+
+    #[test]
+    fn double_press_ignores_key_repeat() {
+        let mut confirmation = Confirmation::new(ConfirmMode::DoublePress { millis: 500 });
+        let start = time::Instant::now();
+        assert!(!confirmation.press(start));
+        // 一直按住时的重复按键不算第二次按下
+        for repeat in [250, 280, 310, 340, 370] {
+            assert!(!confirmation.press(start + millis(repeat)));
+        }
+
+        confirmation.release();
+        assert!(confirmation.press(start + millis(400)));
+    }
+
+    #[test]
+    fn mouse_triggers() {
+        for (text, button, name) in [
+            ("MouseLeft", rdev::Button::Left, "鼠标左键"),
+            ("MouseRight", rdev::Button::Right, "鼠标右键"),
+            ("MouseMiddle", rdev::Button::Middle, "鼠标中键"),
+            ("MouseBack", MOUSE_BACK, "鼠标后退键"),
+            ("MouseForward", MOUSE_FORWARD, "鼠标前进键"),
+            ("Mouse10", rdev::Button::Unknown(10), "鼠标键10"),
+        ] {
+            let trigger: Trigger = text.parse().unwrap();
+            assert_eq!(trigger, Trigger::Mouse(button));
+            assert_eq!(trigger.to_string(), text);
+            assert_eq!(trigger.name(), name);
+        }
+
+        // 侧键的编号和名称是同一个按键
+        assert_eq!("Mouse8".parse(), Ok(Trigger::Mouse(MOUSE_BACK)));
+        assert_eq!(
+            "Mouse".parse::<Trigger>(),
+            Err("未知的鼠标按键 Mouse".to_string())
+        );
+        assert_eq!(
+            "MouseSide".parse::<Trigger>(),
+            Err("未知的鼠标按键 MouseSide".to_string())
+        );
+        assert!("Mouse256".parse::<Trigger>().is_err());
+    }
+
+    #[test]
+    fn gamepad_triggers() {
+        let trigger: Trigger = "PadSouth".parse().unwrap();
+        assert_eq!(trigger, Trigger::Gamepad(gilrs::Button::South));
+        assert_eq!(trigger.to_string(), "PadSouth");
+        assert_eq!(trigger.name(), "手柄South");
+
+        assert_eq!(
+            "PadRightTrigger2".parse(),
+            Ok(Trigger::Gamepad(gilrs::Button::RightTrigger2))
+        );
+        assert_eq!(
+            "PadTurbo".parse::<Trigger>(),
+            Err("未知的手柄按键 PadTurbo".to_string())
+        );
+    }
+
+    #[test]
+    fn modifiers_with_mouse_and_gamepad() {
+        let hotkey: Hotkey = "Ctrl+MouseBack".parse().unwrap();
+        assert_eq!(
+            hotkey,
+            Hotkey::new(
+                Trigger::Mouse(MOUSE_BACK),
+                modifiers(true, false, false, false)
+            )
+        );
+        assert_eq!(hotkey.to_string(), "Ctrl+MouseBack");
+        assert_eq!(hotkey.name(), "Ctrl+鼠标后退键");
+
+        let hotkey: Hotkey = "Shift+PadStart".parse().unwrap();
+        assert_eq!(hotkey.to_string(), "Shift+PadStart");
+    }
+}
@@ -0,0 +1,222 @@
+// phasutils: 为Steam游戏恐鬼症设计的实用工具
+// Copyright (C) 2024  Chen Siyuan
+//
+// This program is free software: you can redistribute it and/or modify
+// it under the terms of the GNU General Public License as published by
+// the Free Software Foundation, either version 3 of the License, or
+// (at your option) any later version.
+//
+// This program is distributed in the hope that it will be useful,
+// but WITHOUT ANY WARRANTY; without even the implied warranty of
+// MERCHANTABILITY or FITNESS FOR A PARTICULAR PURPOSE.  See the
+// GNU General Public License for more details.
+//
+// You should have received a copy of the GNU General Public License
+// along with this program.  If not, see <https://www.gnu.org/licenses/>.
+
+use std::error;
+
+use log::warn;
+use phasutils::font::{self, Coverage};
+use sfml::graphics::{self, RenderTarget, Transformable};
+use sfml::{system, SfBox};
+
+// 按顺序查找字形的一组字体
+pub struct Fonts {
+    fonts: Vec<SfBox<graphics::Font>>,
+    coverage: Coverage,
+}
+
+impl Fonts {
+    pub fn new(data: Vec<&'static [u8]>) -> Result<Fonts, Box<dyn error::Error>> {
+        let mut fonts = vec![];
+        let mut loaded = vec![];
+        for (index, data) in data.into_iter().enumerate() {
+            // 字体数据是 `'static` 的，在字体释放前一直有效
+            match unsafe { graphics::Font::from_memory(data) } {
+                Some(font) => {
+                    fonts.push(font);
+                    loaded.push(data);
+                }
+                None => warn!("无法加载第 {} 个字体", index + 1),
+            }
+        }
+        if fonts.is_empty() {
+            return Err("没有可用的字体".into());
+        }
+
+        Ok(Fonts {
+            fonts,
+            coverage: Coverage::new(&loaded),
+        })
+    }
+
+    pub fn font_for(&self, c: char) -> &graphics::Font {
+        &self.fonts[self.coverage.font_for(c)]
+    }
+
+    // 行距以第一个字体为准
+    pub fn line_spacing(&self, size: u32) -> f32 {
+        self.fonts[0].line_spacing(size)
+    }
+
+    pub fn advance(&self, c: char, size: u32) -> f32 {
+        self.font_for(c)
+            .glyph(c as u32, size, false, 0f32)
+            .advance()
+    }
+}
+
+// 同时包含两个矩形的最小矩形
+pub fn union(a: graphics::FloatRect, b: graphics::FloatRect) -> graphics::FloatRect {
+    let left = a.left.min(b.left);
+    let top = a.top.min(b.top);
+    graphics::FloatRect::new(
+        left,
+        top,
+        (a.left + a.width).max(b.left + b.width) - left,
+        (a.top + a.height).max(b.top + b.height) - top,
+    )
+}
+
+// 可以混用多个字体的文字，接口与 `graphics::Text` 相同
+pub struct Label<'f> {
+    fonts: &'f Fonts,
+    string: String,
+    // 每段文字所在的行和字符数
+    runs: Vec<(usize, usize, graphics::Text<'f>)>,
+    position: system::Vector2f,
+    character_size: u32,
+    style: graphics::TextStyle,
+    fill_color: graphics::Color,
+    outline_color: graphics::Color,
+    outline_thickness: f32,
+}
+
+impl<'f> Label<'f> {
+    pub fn new(string: &str, fonts: &'f Fonts, character_size: u32) -> Label<'f> {
+        let mut label = Label {
+            fonts,
+            string: string.to_string(),
+            runs: vec![],
+            position: system::Vector2f::default(),
+            character_size,
+            style: graphics::TextStyle::REGULAR,
+            fill_color: graphics::Color::WHITE,
+            outline_color: graphics::Color::BLACK,
+            outline_thickness: 0.0,
+        };
+        label.rebuild();
+
+        label
+    }
+
+    fn rebuild(&mut self) {
+        self.runs.clear();
+        for (line_index, line) in self.string.split('\n').enumerate() {
+            let runs = font::runs(line, |c| self.fonts.coverage.font_for(c));
+            for (font_index, range) in runs {
+                let run = &line[range];
+                let mut text =
+                    graphics::Text::new(run, &self.fonts.fonts[font_index], self.character_size);
+                text.set_style(graphics::TextStyle::from_bits_retain(self.style.bits()));
+                text.set_fill_color(self.fill_color);
+                text.set_outline_color(self.outline_color);
+                text.set_outline_thickness(self.outline_thickness);
+                self.runs.push((line_index, run.chars().count(), text));
+            }
+        }
+        self.arrange();
+    }
+
+    // 同一行的各段首尾相接，换行由这里处理，不交给 SFML
+    fn arrange(&mut self) {
+        let line_spacing = self.fonts.line_spacing(self.character_size);
+        let mut current_line = None;
+        let mut pen = self.position;
+        for (line_index, count, text) in &mut self.runs {
+            if current_line != Some(*line_index) {
+                current_line = Some(*line_index);
+                pen = self.position + system::Vector2f::new(0.0, line_spacing * *line_index as f32);
+            }
+            text.set_position(pen);
+            pen.x = text.find_character_pos(*count).x;
+        }
+    }
+
+    pub fn set_string(&mut self, string: &str) {
+        if self.string != string {
+            self.string = string.to_string();
+            self.rebuild();
+        }
+    }
+
+    pub fn set_character_size(&mut self, character_size: u32) {
+        if self.character_size != character_size {
+            self.character_size = character_size;
+            self.rebuild();
+        }
+    }
+
+    pub fn set_style(&mut self, style: graphics::TextStyle) {
+        if self.style.bits() != style.bits() {
+            self.style = style;
+            self.rebuild();
+        }
+    }
+
+    pub fn set_position(&mut self, position: system::Vector2f) {
+        self.position = position;
+        self.arrange();
+    }
+
+    pub fn position(&self) -> system::Vector2f {
+        self.position
+    }
+
+    pub fn set_fill_color(&mut self, color: graphics::Color) {
+        self.fill_color = color;
+        for (_, _, text) in &mut self.runs {
+            text.set_fill_color(color);
+        }
+    }
+
+    pub fn fill_color(&self) -> graphics::Color {
+        self.fill_color
+    }
+
+    pub fn set_outline_color(&mut self, color: graphics::Color) {
+        self.outline_color = color;
+        for (_, _, text) in &mut self.runs {
+            text.set_outline_color(color);
+        }
+    }
+
+    pub fn set_outline_thickness(&mut self, thickness: f32) {
+        self.outline_thickness = thickness;
+        for (_, _, text) in &mut self.runs {
+            text.set_outline_thickness(thickness);
+        }
+    }
+
+    // 空字符串的大小为 0
+    pub fn global_bounds(&self) -> graphics::FloatRect {
+        self.runs
+            .iter()
+            .map(|(_, _, text)| text.global_bounds())
+            .filter(|bounds| bounds.width > 0.0)
+            .reduce(union)
+            .unwrap_or(graphics::FloatRect::new(
+                self.position.x,
+                self.position.y,
+                0.0,
+                0.0,
+            ))
+    }
+
+    pub fn draw(&self, target: &mut graphics::RenderTexture) {
+        for (_, _, text) in &self.runs {
+            target.draw(text);
+        }
+    }
+}
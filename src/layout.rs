@@ -0,0 +1,295 @@
+// phasutils: 为Steam游戏恐鬼症设计的实用工具
+// Copyright (C) 2024  Chen Siyuan
+//
+// This program is free software: you can redistribute it and/or modify
+// it under the terms of the GNU General Public License as published by
+// the Free Software Foundation, either version 3 of the License, or
+// (at your option) any later version.
+//
+// This program is distributed in the hope that it will be useful,
+// but WITHOUT ANY WARRANTY; without even the implied warranty of
+// MERCHANTABILITY or FITNESS FOR A PARTICULAR PURPOSE.  See the
+// GNU General Public License for more details.
+//
+// You should have received a copy of the GNU General Public License
+// along with this program.  If not, see <https://www.gnu.org/licenses/>.
+
+use serde::{Deserialize, Serialize};
+
+use crate::app::AppState;
+
+// 悬浮窗中的各组文字
+#[derive(Serialize, Deserialize, Debug, Clone, Copy, PartialEq, Eq)]
+#[serde(rename_all = "snake_case")]
+pub enum Section {
+    // 标题和热键状态
+    Title,
+    // 计时器和分段记录
+    Timers,
+    Speed,
+    Tips,
+    Evidence,
+    // 鬼魂名称、特性和页码
+    Ghost,
+    // 剩余候选鬼魂的数量
+    Candidates,
+}
+
+impl Section {
+    // 鬼魂特性按剩下的高度分页，其他区块的高度由内容决定
+    pub fn fills(self) -> bool {
+        self == Section::Ghost
+    }
+}
+
+#[derive(Serialize, Deserialize, Debug, Clone, Copy, PartialEq, Eq, Default)]
+#[serde(rename_all = "snake_case")]
+pub enum Condition {
+    #[default]
+    Always,
+    Never,
+    Armed,
+    // 任意一个计时器正在运行
+    TimerRunning,
+    TimerStopped,
+    SpeedMeasured,
+}
+
+impl Condition {
+    pub fn is_met(self, state: &AppState) -> bool {
+        let timer_running = state.timers().iter().any(|timer| timer.is_running());
+        match self {
+            Condition::Always => true,
+            Condition::Never => false,
+            Condition::Armed => state.is_armed(),
+            Condition::TimerRunning => timer_running,
+            Condition::TimerStopped => !timer_running,
+            Condition::SpeedMeasured => state.speed().is_some(),
+        }
+    }
+}
+
+#[derive(Serialize, Deserialize, Debug, Clone, Copy, PartialEq, Eq)]
+pub struct Block {
+    pub section: Section,
+    // 缺省时为 10，计时器缺省使用各自配置的字号
+    #[serde(default)]
+    pub font_size: Option<u32>,
+    #[serde(default)]
+    pub visible: Condition,
+    // 只对计时器有效，隐藏没有运行的计时器
+    #[serde(default)]
+    pub running_only: bool,
+}
+
+impl Block {
+    pub fn new(section: Section) -> Block {
+        Block {
+            section,
+            font_size: None,
+            visible: Condition::Always,
+            running_only: false,
+        }
+    }
+}
+
+// 区块从上到下排成一列，尺寸与窗口一样会乘以 `scale`
+#[derive(Serialize, Deserialize, Debug, Clone, PartialEq)]
+#[serde(default)]
+pub struct Layout {
+    // 窗口边缘的留白
+    pub padding: u32,
+    // 相邻区块的间距
+    pub spacing: u32,
+    pub blocks: Vec<Block>,
+}
+
+impl Default for Layout {
+    fn default() -> Self {
+        Layout {
+            padding: 10,
+            spacing: 5,
+            blocks: vec![
+                Block::new(Section::Title),
+                Block::new(Section::Timers),
+                Block::new(Section::Speed),
+                // 开始计时后把空间让给鬼魂特性，熟悉按键后可以在 settings.json 中改为 "never"
+                Block {
+                    visible: Condition::TimerStopped,
+                    ..Block::new(Section::Tips)
+                },
+                Block::new(Section::Evidence),
+                Block::new(Section::Ghost),
+            ],
+        }
+    }
+}
+
+impl Layout {
+    // 精简模式只显示正在运行的计时器和候选数量
+    pub fn compact() -> Layout {
+        Layout {
+            padding: 5,
+            spacing: 3,
+            blocks: vec![
+                Block {
+                    running_only: true,
+                    ..Block::new(Section::Timers)
+                },
+                Block::new(Section::Candidates),
+            ],
+        }
+    }
+}
+
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub struct Placement {
+    pub block: Block,
+    // 实际像素
+    pub top: f32,
+    pub height: f32,
+}
+
+impl Layout {
+    // `height` 和 `measure` 返回的高度都是实际像素，占满剩余高度的区块不调用 `measure`
+    pub fn arrange<V, M>(
+        &self,
+        height: f32,
+        scale: u32,
+        mut is_visible: V,
+        mut measure: M,
+    ) -> Vec<Placement>
+    where
+        V: FnMut(&Block) -> bool,
+        M: FnMut(&Block) -> f32,
+    {
+        let padding = (self.padding * scale) as f32;
+        let spacing = (self.spacing * scale) as f32;
+
+        let blocks: Vec<Block> = self
+            .blocks
+            .iter()
+            .filter(|block| is_visible(block))
+            .copied()
+            .collect();
+        let heights: Vec<Option<f32>> = blocks
+            .iter()
+            .map(|block| (!block.section.fills()).then(|| measure(block)))
+            .collect();
+
+        let used =
+            heights.iter().flatten().sum::<f32>() + spacing * blocks.len().saturating_sub(1) as f32;
+        let fills = heights.iter().filter(|height| height.is_none()).count();
+        let rest = ((height - padding * 2.0 - used) / fills.max(1) as f32).max(0.0);
+
+        let mut top = padding;
+        blocks
+            .into_iter()
+            .zip(heights)
+            .map(|(block, height)| {
+                let height = height.unwrap_or(rest);
+                let placement = Placement { block, top, height };
+                top += height + spacing;
+                placement
+            })
+            .collect()
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use std::time;
+
+    use super::*;
+    use crate::app::Command;
+    use crate::config::{Config, TimerConfig};
+    use crate::input::{Bindings, Trigger};
+    use crate::source::InputEvent;
+
+    fn sections(placements: &[Placement]) -> Vec<Section> {
+        placements
+            .iter()
+            .map(|placement| placement.block.section)
+            .collect()
+    }
+
+    #[test]
+    fn stack_blocks_in_order() {
+        let layout = Layout::default();
+        let placements = layout.arrange(300.0, 1, |_| true, |_| 20.0);
+        assert_eq!(
+            sections(&placements),
+            [
+                Section::Title,
+                Section::Timers,
+                Section::Speed,
+                Section::Tips,
+                Section::Evidence,
+                Section::Ghost,
+            ]
+        );
+
+        let tops: Vec<f32> = placements.iter().map(|placement| placement.top).collect();
+        assert_eq!(tops, [10.0, 35.0, 60.0, 85.0, 110.0, 135.0]);
+        // 300 - 上下留白 20 - 五个区块 100 - 五个间距 25
+        assert_eq!(placements[5].height, 155.0);
+    }
+
+    #[test]
+    fn hidden_blocks_give_space_to_ghost() {
+        let layout = Layout::default();
+        let placements = layout.arrange(300.0, 1, |block| block.section != Section::Tips, |_| 20.0);
+        assert!(!sections(&placements).contains(&Section::Tips));
+        assert_eq!(placements.last().unwrap().top, 110.0);
+        assert_eq!(placements.last().unwrap().height, 180.0);
+    }
+
+    #[test]
+    fn scale_padding_and_spacing() {
+        let layout = Layout {
+            padding: 2,
+            spacing: 1,
+            blocks: vec![Block::new(Section::Title), Block::new(Section::Timers)],
+        };
+        let placements = layout.arrange(100.0, 3, |_| true, |_| 30.0);
+        assert_eq!(placements[0].top, 6.0);
+        assert_eq!(placements[1].top, 39.0);
+    }
+
+    #[test]
+    fn ghost_never_gets_negative_height() {
+        let layout = Layout::default();
+        let placements = layout.arrange(100.0, 1, |_| true, |_| 50.0);
+        assert_eq!(placements.last().unwrap().height, 0.0);
+    }
+
+    #[test]
+    fn default_tips_hide_while_timing() {
+        let mut state = AppState::new(Config {
+            version: 2,
+            bindings: Bindings::default(),
+            timers: vec![TimerConfig {
+                id: "main".to_string(),
+                start: Some(rdev::Key::Num1.into()),
+                ..Default::default()
+            }],
+            presets: vec![],
+            idle_frame_rate: 10,
+            auto_scroll_seconds: 8,
+            ghosts: vec![],
+        });
+        let layout = Layout::default();
+        let tips = layout
+            .blocks
+            .iter()
+            .find(|block| block.section == Section::Tips)
+            .unwrap();
+        assert!(tips.visible.is_met(&state));
+
+        state.reduce(Command::Input(
+            InputEvent::Press(Trigger::Key(rdev::Key::Num1)),
+            time::Instant::now(),
+        ));
+        assert!(!tips.visible.is_met(&state));
+    }
+}
@@ -0,0 +1,30 @@
+// phasutils: 为Steam游戏恐鬼症设计的实用工具
+// Copyright (C) 2024  Chen Siyuan
+//
+// This program is free software: you can redistribute it and/or modify
+// it under the terms of the GNU General Public License as published by
+// the Free Software Foundation, either version 3 of the License, or
+// (at your option) any later version.
+//
+// This program is distributed in the hope that it will be useful,
+// but WITHOUT ANY WARRANTY; without even the implied warranty of
+// MERCHANTABILITY or FITNESS FOR A PARTICULAR PURPOSE.  See the
+// GNU General Public License for more details.
+//
+// You should have received a copy of the GNU General Public License
+// along with this program.  If not, see <https://www.gnu.org/licenses/>.
+
+pub mod app;
+pub mod config;
+pub mod deduction;
+pub mod evidence;
+pub mod font;
+pub mod input;
+pub mod layout;
+pub mod redraw;
+pub mod settings;
+pub mod source;
+pub mod speed;
+pub mod theme;
+pub mod timer;
+pub mod wrap;
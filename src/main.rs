@@ -16,303 +16,706 @@
 
 #![cfg_attr(not(debug_assertions), windows_subsystem = "windows")]
 
-use std::sync::atomic::{self, Ordering};
-use std::{fs, sync, thread, time};
-use log::info;
+mod label;
+mod overlay;
 
-use rdev::{listen, Event};
-use serde::{Deserialize, Serialize};
-use sfml::graphics::{RenderTarget, Transformable};
+use std::sync::mpsc;
+use std::{fs, iter, thread, time};
+use log::{info, warn};
+
+use sfml::graphics::{RenderTarget, Shape};
+use sfml::window::mouse;
 use sfml::{graphics, system, window};
-use windows::Win32::Foundation::{COLORREF, HWND};
-use windows::Win32::UI::WindowsAndMessaging::{
-    GetWindowLongW, SetLayeredWindowAttributes, SetWindowLongW, SetWindowPos, GWL_EXSTYLE,
-    HWND_TOPMOST, LWA_COLORKEY, SWP_NOMOVE, SWP_NOSIZE, WS_EX_LAYERED,
-};
-
-const SCALE: u32 = 3;
-const TEXT_COLOR: graphics::Color = graphics::Color::rgb(0x66, 0xcc, 0xff);
-const TEXT_COLOR_HIGHLIGHT: graphics::Color = graphics::Color::rgb(0xff, 0xd7, 0x00);
-
-struct StopWatch {
-    elapsed: time::Duration,
-    start: bool,
-    instant: time::Instant,
+
+use phasutils::app::{AppState, Command};
+use phasutils::config::{self, Config, CONFIG_FILE};
+use phasutils::deduction::EvidenceState;
+use phasutils::evidence::Evidence;
+use phasutils::input::ConfirmMode;
+use phasutils::layout::{Block, Section};
+use phasutils::redraw::RedrawScheduler;
+use phasutils::settings::{Monitor, Settings, WindowSettings, SETTINGS_FILE};
+use phasutils::source;
+use phasutils::theme::{self, Theme};
+use phasutils::timer::{Timer, FLASH_INTERVAL};
+use phasutils::wrap;
+
+use label::{Fonts, Label};
+use overlay::{OverlayWindow, PlatformOverlay};
+
+const EMBEDDED_FONT: &[u8] = include_bytes!("../assets/font.ttf");
+
+fn color(color: theme::Color) -> graphics::Color {
+    graphics::Color::rgba(color.r, color.g, color.b, color.a)
 }
 
-impl StopWatch {
-    fn new() -> StopWatch {
-        StopWatch {
-            elapsed: time::Duration::new(0, 0),
-            start: false,
-            instant: time::Instant::now(),
+// 每个圆角用几个点近似
+const CORNER_POINTS: usize = 6;
+
+// 按主题在一组文字后面画圆角底板
+fn draw_panel<'a, 's: 'a>(
+    target: &mut graphics::RenderTexture,
+    texts: impl IntoIterator<Item = &'a Label<'s>>,
+    theme: &Theme,
+    scale: u32,
+) {
+    let Some(panel) = &theme.panel else {
+        return;
+    };
+    let Some(bounds) = texts
+        .into_iter()
+        .map(|text| text.global_bounds())
+        .filter(|bounds| bounds.width > 0.0)
+        .reduce(label::union)
+    else {
+        return;
+    };
+
+    let padding = panel.padding * scale as f32;
+    let (left, top) = (bounds.left - padding, bounds.top - padding);
+    let (right, bottom) = (
+        bounds.left + bounds.width + padding,
+        bounds.top + bounds.height + padding,
+    );
+    let radius = (panel.radius * scale as f32)
+        .min((right - left) / 2.0)
+        .min((bottom - top) / 2.0);
+
+    // 从右上角开始顺时针排列四个圆角的点
+    let corners = [
+        (right - radius, top + radius, -90.0f32),
+        (right - radius, bottom - radius, 0.0),
+        (left + radius, bottom - radius, 90.0),
+        (left + radius, top + radius, 180.0),
+    ];
+    let mut shape = graphics::ConvexShape::new(corners.len() * CORNER_POINTS);
+    for (corner, (x, y, start)) in corners.into_iter().enumerate() {
+        for point in 0..CORNER_POINTS {
+            let angle = (start + 90.0 * point as f32 / (CORNER_POINTS - 1) as f32).to_radians();
+            shape.set_point(
+                corner * CORNER_POINTS + point,
+                system::Vector2f::new(x + radius * angle.cos(), y + radius * angle.sin()),
+            );
         }
     }
+    shape.set_fill_color(color(panel.color));
+    target.draw(&shape);
+}
 
-    fn start(&mut self) {
-        if !self.start {
-            self.start = true;
-            self.instant = time::Instant::now();
-        }
+// 按主题加上描边和阴影后绘制
+fn draw_text(target: &mut graphics::RenderTexture, text: &mut Label, theme: &Theme, scale: u32) {
+    text.set_outline_color(color(theme.outline));
+    text.set_outline_thickness(theme.outline_thickness * scale as f32);
+
+    if let Some(shadow) = &theme.shadow {
+        let position = text.position();
+        let fill_color = text.fill_color();
+        text.set_position(
+            position
+                + system::Vector2f::new(
+                    shadow.offset_x * scale as f32,
+                    shadow.offset_y * scale as f32,
+                ),
+        );
+        text.set_fill_color(color(shadow.color));
+        text.set_outline_color(color(shadow.color));
+        text.draw(target);
+
+        text.set_position(position);
+        text.set_fill_color(fill_color);
+        text.set_outline_color(color(theme.outline));
     }
 
-    fn stop(&mut self) {
-        if self.start {
-            self.start = false;
-            self.elapsed += self.instant.elapsed();
+    text.draw(target);
+}
+
+// 布局没有指定字号时使用的字号
+const DEFAULT_FONT_SIZE: u32 = 10;
+
+// 悬浮窗上的所有文字，位置由布局决定
+struct Texts<'f> {
+    title: Label<'f>,
+    armed: Label<'f>,
+    timers: Vec<Label<'f>>,
+    laps: Vec<Option<Label<'f>>>,
+    speed: Label<'f>,
+    tips: Vec<Label<'f>>,
+    evidence_count: Label<'f>,
+    evidence: Vec<Label<'f>>,
+    ghost_name: Label<'f>,
+    ghost_features: Label<'f>,
+    features_page: Label<'f>,
+    candidates: Label<'f>,
+}
+
+impl<'f> Texts<'f> {
+    fn section(&mut self, block: &Block, timers: &[Timer]) -> Vec<&mut Label<'f>> {
+        match block.section {
+            Section::Title => vec![&mut self.title, &mut self.armed],
+            Section::Timers => self
+                .timers
+                .iter_mut()
+                .zip(&mut self.laps)
+                .zip(timers)
+                .filter(|(_, timer)| !block.running_only || timer.is_running())
+                .flat_map(|((text, lap), _)| iter::once(text).chain(lap))
+                .collect(),
+            Section::Speed => vec![&mut self.speed],
+            Section::Tips => self.tips.iter_mut().collect(),
+            Section::Evidence => iter::once(&mut self.evidence_count)
+                .chain(&mut self.evidence)
+                .collect(),
+            Section::Ghost => vec![
+                &mut self.ghost_name,
+                &mut self.ghost_features,
+                &mut self.features_page,
+            ],
+            Section::Candidates => vec![&mut self.candidates],
         }
     }
 
-    fn reset(&mut self) {
-        if !self.start {
-            self.elapsed = time::Duration::new(0, 0);
-            self.instant = time::Instant::now();
+    // 把区块的文字放进 `area`，返回内容的高度；页码在设置内容后再靠右对齐
+    fn place(
+        &mut self,
+        block: &Block,
+        area: graphics::FloatRect,
+        fonts: &Fonts,
+        timers: &[Timer],
+        scale: u32,
+    ) -> f32 {
+        let size = block.font_size.unwrap_or(DEFAULT_FONT_SIZE) * scale;
+        let line_spacing = fonts.line_spacing(size);
+        let gap = (5 * scale) as f32;
+        for text in self.section(block, timers) {
+            text.set_character_size(size);
         }
-    }
 
-    fn elapsed(&self) -> time::Duration {
-        self.elapsed
-            + if self.start {
-                self.instant.elapsed()
-            } else {
-                time::Duration::new(0, 0)
+        match block.section {
+            Section::Title => {
+                self.title
+                    .set_position(system::Vector2f::new(area.left, area.top));
+                self.armed.set_position(system::Vector2f::new(
+                    area.left + self.title.global_bounds().width + gap,
+                    area.top,
+                ));
+                line_spacing
             }
+            Section::Timers => {
+                let mut bottom = area.top;
+                for (index, ((text, lap), timer)) in self
+                    .timers
+                    .iter_mut()
+                    .zip(&mut self.laps)
+                    .zip(timers)
+                    .filter(|(_, timer)| !block.running_only || timer.is_running())
+                    .enumerate()
+                {
+                    if index > 0 {
+                        bottom += gap;
+                    }
+                    let timer_size = block.font_size.unwrap_or(timer.config().font_size) * scale;
+                    text.set_character_size(timer_size);
+                    text.set_position(system::Vector2f::new(area.left, bottom));
+                    bottom += fonts.line_spacing(timer_size);
+
+                    // 为分段列表预留固定的高度
+                    if let Some(lap) = lap {
+                        lap.set_position(system::Vector2f::new(area.left, bottom));
+                        bottom += line_spacing * timer.config().visible_laps as f32;
+                    }
+                }
+                bottom - area.top
+            }
+            Section::Speed => {
+                self.speed
+                    .set_position(system::Vector2f::new(area.left, area.top));
+                line_spacing
+            }
+            Section::Tips => {
+                for (index, text) in self.tips.iter_mut().enumerate() {
+                    text.set_position(system::Vector2f::new(
+                        area.left,
+                        area.top + line_spacing * index as f32,
+                    ));
+                }
+                line_spacing * self.tips.len() as f32
+            }
+            Section::Evidence => {
+                // 一行放不下时换行
+                let mut position = system::Vector2f::new(area.left, area.top);
+                for text in iter::once(&mut self.evidence_count).chain(&mut self.evidence) {
+                    let width = text.global_bounds().width;
+                    if position.x > area.left && position.x + width > area.left + area.width {
+                        position = system::Vector2f::new(area.left, position.y + line_spacing);
+                    }
+                    text.set_position(position);
+                    position.x += width + gap;
+                }
+                position.y + line_spacing - area.top
+            }
+            Section::Ghost => {
+                self.ghost_name
+                    .set_position(system::Vector2f::new(area.left, area.top));
+                self.ghost_features
+                    .set_position(system::Vector2f::new(area.left, area.top + line_spacing));
+                area.height
+            }
+            Section::Candidates => {
+                self.candidates
+                    .set_position(system::Vector2f::new(area.left, area.top));
+                line_spacing
+            }
+        }
     }
-}
 
-#[derive(Serialize, Deserialize, Debug)]
-struct GhostInformation {
-    id: String,
-    name: String,
-    speed: String,
-    features: String,
+    fn draw(
+        &mut self,
+        block: &Block,
+        timers: &[Timer],
+        target: &mut graphics::RenderTexture,
+        theme: &Theme,
+        scale: u32,
+    ) {
+        let mut texts = self.section(block, timers);
+        draw_panel(target, texts.iter().map(|text| &**text), theme, scale);
+        for text in &mut texts {
+            draw_text(target, text, theme, scale);
+        }
+    }
 }
 
-#[derive(Serialize, Deserialize, Debug)]
-struct Config {
-    version: i32,
-    ghosts: Vec<GhostInformation>,
+// 找不到设置中的显示器时使用第一个显示器
+fn find_monitor(monitors: &[Monitor], window: &WindowSettings) -> Monitor {
+    match monitors.get(window.monitor) {
+        Some(monitor) => *monitor,
+        None => {
+            warn!("找不到第 {} 个显示器，使用第一个显示器", window.monitor);
+            let (width, height) = window.size();
+            monitors.first().copied().unwrap_or(Monitor {
+                x: 0,
+                y: 0,
+                width,
+                height,
+            })
+        }
+    }
 }
 
 fn main() -> Result<(), Box<dyn std::error::Error>> {
     env_logger::init();
 
-    info!("加载配置文件中");
-    let config: sync::Arc<sync::RwLock<Config>> = sync::Arc::new(sync::RwLock::new(
-        serde_json::from_str(&fs::read_to_string("./config.json")?)?,
-    ));
+    let config_dir = config::config_dir();
+    info!("加载配置文件中: {}", config_dir.display());
+    let mut state = AppState::new(Config::load(config_dir.join(CONFIG_FILE))?);
+    let settings_path = config_dir.join(SETTINGS_FILE);
+    let mut settings = Settings::load(&settings_path)?;
+    let mut scale = settings.window.scale;
+    let theme = settings.theme();
+    let text_color = color(theme.text);
+    let highlight_color = color(theme.highlight);
+    let warning_color = color(theme.warning);
+    let excluded_color = color(theme.excluded);
 
     let mut window = graphics::RenderWindow::new(
-        (200 * SCALE, 300 * SCALE),
+        settings.window.size(),
         "Phasutils",
         window::Style::NONE,
         &window::ContextSettings::default(),
     );
     let mut window_background = graphics::RenderWindow::new(
-        (200 * SCALE, 300 * SCALE),
+        settings.window.size(),
         "Phasutils",
         window::Style::NONE,
         &window::ContextSettings::default(),
     );
-    let window_should_close = sync::Arc::new(atomic::AtomicBool::new(false));
-
-    let h_wnd = HWND(window.system_handle() as isize);
 
-    unsafe {
-        SetWindowLongW(
-            h_wnd,
-            GWL_EXSTYLE,
-            GetWindowLongW(h_wnd, GWL_EXSTYLE) | WS_EX_LAYERED.0 as i32,
-        );
-        SetLayeredWindowAttributes(h_wnd, COLORREF::default(), 0, LWA_COLORKEY)?;
+    // 先画到带透明通道的画布上，再整张交给悬浮窗
+    let (width, height) = settings.window.size();
+    let mut canvas = graphics::RenderTexture::new(width, height).ok_or("无法创建画布")?;
+
+    let mut overlay = PlatformOverlay::new(&window)?;
+
+    let mut position: system::Vector2i = settings
+        .window
+        .position(&find_monitor(&overlay.monitors(), &settings.window))
+        .into();
+    overlay.set_position(position);
+
+    // 用户字体在前，内置字体作为最后的后备
+    let mut font_data: Vec<&'static [u8]> = vec![];
+    for path in &settings.fonts {
+        match fs::read(config_dir.join(path)) {
+            // 字体在整个运行期间都要使用
+            Ok(data) => font_data.push(Box::leak(data.into_boxed_slice())),
+            Err(err) => warn!("无法读取字体 {}: {}", path.display(), err),
+        }
     }
+    font_data.push(EMBEDDED_FONT);
+    let fonts = Fonts::new(font_data)?;
+
+    // 字号和位置都由布局决定
+    let mut layout = settings.layout.clone();
+    let size = DEFAULT_FONT_SIZE * scale;
+    let timers = state.timers();
+    // 计时器运行时高亮对应的开始提示
+    let tips = state.tips();
+    let mut texts = Texts {
+        title: {
+            let mut text = Label::new("PHASUTILS by Cg1340", &fonts, size);
+            text.set_style(graphics::TextStyle::ITALIC);
+            text
+        },
+        armed: Label::new("热键已启用", &fonts, size),
+        timers: timers
+            .iter()
+            .map(|timer| {
+                let mut text = Label::new(&timer.text(), &fonts, size);
+                text.set_fill_color(text_color);
+                text
+            })
+            .collect(),
+        laps: timers
+            .iter()
+            .map(|timer| {
+                timer.config().lap.map(|_| {
+                    let mut text = Label::new("", &fonts, size);
+                    text.set_fill_color(text_color);
+                    text
+                })
+            })
+            .collect(),
+        speed: {
+            let mut text = Label::new("脚步测速 --", &fonts, size);
+            text.set_fill_color(text_color);
+            text
+        },
+        tips: tips
+            .iter()
+            .map(|(tip, _)| {
+                let mut text = Label::new(tip, &fonts, size);
+                text.set_fill_color(text_color);
+                text
+            })
+            .collect(),
+        evidence_count: {
+            let mut text = Label::new("3证据", &fonts, size);
+            text.set_fill_color(highlight_color);
+            text
+        },
+        evidence: Evidence::ALL
+            .iter()
+            .map(|evidence| Label::new(evidence.name(), &fonts, size))
+            .collect(),
+        ghost_name: Label::new("", &fonts, size),
+        ghost_features: Label::new("", &fonts, size),
+        features_page: {
+            let mut text = Label::new("", &fonts, size);
+            text.set_fill_color(text_color);
+            text
+        },
+        candidates: {
+            let mut text = Label::new("", &fonts, size);
+            text.set_fill_color(text_color);
+            text
+        },
+    };
+
+    let (command_sender, command_receiver) = mpsc::channel();
+    source::spawn_keyboard_mouse(command_sender.clone());
+    source::spawn_gamepad(command_sender);
+
+    let mut hidden = false;
+    // 鬼魂信息没有变化时不重新排版
+    let mut ghost_name = String::new();
+    let mut feature_lines: Vec<String> = vec![];
+    let mut lines_per_page = 0;
+    let mut compact = false;
+    // 开始拖动时的鼠标和窗口位置
+    let mut drag_start: Option<(system::Vector2i, system::Vector2i)> = None;
+    let mut redraw = RedrawScheduler::new(state.config().idle_frame_rate);
+    loop {
+        while let Some(event) = window.poll_event() {
+            match event {
+                window::Event::Closed => state.reduce(Command::Close),
+                _ => redraw.invalidate(),
+            }
+        }
 
-    window.set_position((0, 0).into());
-    window.set_framerate_limit(200);
-
-    let font = graphics::Font::from_file("./assets/font.ttf").unwrap();
-
-    // --- 标题 --- //
+        // 等到下一条命令、下一次计时器进位或下一个空闲帧
+        let timeout = redraw.timeout(time::Instant::now());
+        match command_receiver.recv_timeout(timeout) {
+            Ok(command) => state.reduce(command),
+            Err(mpsc::RecvTimeoutError::Timeout) => {}
+            // 输入线程都已退出，只按空闲帧率刷新
+            Err(mpsc::RecvTimeoutError::Disconnected) => thread::sleep(timeout),
+        }
+        for command in command_receiver.try_iter() {
+            state.reduce(command);
+        }
 
-    let mut text_title = graphics::Text::new("PHASUTILS by Cg1340", &font, 10 * SCALE);
-    text_title.set_style(graphics::TextStyle::ITALIC);
-    text_title.set_position(system::Vector2f::new(
-        (10 * SCALE) as f32,
-        (10 * SCALE) as f32,
-    ));
+        let now = time::Instant::now();
+        state.reduce(Command::Tick(now));
+        if state.should_close() {
+            break;
+        }
 
-    // --- 计时器 --- //
+        // 拖动时窗口跟着鼠标移动，松开后保存位置
+        if state.is_dragging() {
+            let cursor = mouse::desktop_position();
+            let (cursor_start, window_start) = *drag_start.get_or_insert((cursor, position));
+            position = window_start + cursor - cursor_start;
+            overlay.set_position(position);
+        } else if drag_start.take().is_some() {
+            settings
+                .window_mut(compact)
+                .move_to(&overlay.monitors(), (position.x, position.y));
+            if let Err(err) = settings.save_window(&settings_path, compact) {
+                warn!("无法保存设置文件: {}", err);
+            }
+        }
 
-    let mut text_timer = graphics::Text::new("00:00", &font, 40 * SCALE);
-    text_timer.set_fill_color(TEXT_COLOR);
-    text_timer.set_position(system::Vector2f::new(
-        (10 * SCALE) as f32,
-        text_title.global_bounds().top + text_title.global_bounds().height,
-    ));
+        // 切换模式时换成对应的窗口大小、位置和布局
+        if compact != state.is_compact() {
+            compact = !compact;
+            drag_start = None;
+            let window_settings = settings.window(compact);
+            let (width, height) = window_settings.size();
+            canvas = graphics::RenderTexture::new(width, height).ok_or("无法创建画布")?;
+            overlay.set_size(system::Vector2u::new(width, height));
+            position = window_settings
+                .position(&find_monitor(&overlay.monitors(), window_settings))
+                .into();
+            overlay.set_position(position);
+            scale = window_settings.scale;
+            layout = settings.layout(compact).clone();
+            // 特性的宽度和每页行数都可能变化，需要重新排版
+            lines_per_page = 0;
+        }
 
-    let tips = [
-        "[1] 键开始计时",
-        "[2] 键停止计时",
-        "[3] 键重置",
-        "[0] 键退出",
-        "[Z/X] 键切换到上/下一个鬼魂特性",
-    ];
-    let mut text_tips: Vec<graphics::Text> = vec![];
-    for (idx, tip) in tips.iter().enumerate() {
-        let mut text = graphics::Text::new(*tip, &font, 10 * SCALE);
-        text.set_fill_color(TEXT_COLOR);
-        if idx == 0 {
-            text.set_position(system::Vector2f::new(
-                (10 * SCALE) as f32,
-                text_timer.global_bounds().top
-                    + text_timer.global_bounds().height
-                    + (10 * SCALE) as f32,
-            ));
-        } else {
-            text.set_position(system::Vector2f::new(
-                (10 * SCALE) as f32,
-                text_tips[idx - 1].global_bounds().top + text_tips[idx - 1].global_bounds().height,
-            ));
+        if hidden != state.is_hidden() {
+            hidden = !hidden;
+            overlay.set_visible(!hidden);
+        }
+        // 隐藏时也要推进重绘时间，否则会一直立即超时
+        let should_redraw = redraw.should_redraw(&state, now);
+        if hidden {
+            continue;
         }
 
-        text_tips.push(text);
-    }
+        overlay.keep_on_top()?;
+        if !should_redraw {
+            continue;
+        }
 
-    let stopwatch = sync::Arc::new(sync::RwLock::new(StopWatch::new()));
-    let stopwatch_clone = stopwatch.clone();
-
-    // --- 鬼魂信息 --- //
-    let mut text_ghost_name = graphics::Text::new("[GHOST_NAME]", &font, 10 * SCALE);
-    text_ghost_name.set_position(system::Vector2f::new(
-        (10 * SCALE) as f32,
-        text_tips.last().unwrap().global_bounds().top
-            + text_tips.last().unwrap().global_bounds().height
-            + (10 * SCALE) as f32,
-    ));
-
-    let mut text_ghost_features = graphics::Text::new("[GHOST_FEATURES]", &font, 10 * SCALE);
-    text_ghost_features.set_position(system::Vector2f::new(
-        (10 * SCALE) as f32,
-        text_ghost_name.global_bounds().top + text_ghost_name.global_bounds().height,
-    ));
-
-    let index = sync::Arc::new(atomic::AtomicUsize::new(0));
-    let ghost_information_should_update = sync::Arc::new(atomic::AtomicBool::new(true));
-
-    let config_clone = config.clone();
-    let index_clone = index.clone();
-    let ghost_information_should_update_clone = ghost_information_should_update.clone();
-    let window_should_close_clone = window_should_close.clone();
-    thread::spawn(move || {
-        let stopwatch = stopwatch_clone;
-
-        let callback = move |event: Event| {
-            if let rdev::EventType::KeyPress(key) = event.event_type {
-                let mut stopwatch = stopwatch.write().unwrap();
-                match key {
-                    rdev::Key::Num1 => stopwatch.start(),
-                    rdev::Key::Num2 => stopwatch.stop(),
-                    rdev::Key::Num3 => stopwatch.reset(),
-                    rdev::Key::Num0 => window_should_close_clone.store(true, Ordering::Relaxed),
-                    rdev::Key::KeyZ => {
-                        if index_clone.load(Ordering::Relaxed) != 0 {
-                            index_clone.fetch_sub(1, Ordering::Relaxed);
-                            ghost_information_should_update_clone.store(true, Ordering::Relaxed);
-                        }
-                    }
-                    rdev::Key::KeyX => {
-                        if index_clone.load(Ordering::Relaxed)
-                            != config_clone.read().unwrap().ghosts.len() - 1
-                        {
-                            index_clone.fetch_add(1, Ordering::Relaxed);
-                            ghost_information_should_update_clone.store(true, Ordering::Relaxed);
-                        }
-                    }
-                    _ => {}
-                }
-            }
+        let (name, features) = match state.current_ghost() {
+            Some((index, ghost_information)) => (
+                format!(
+                    "[{}/{}] {} ({}) {}",
+                    index + 1,
+                    state.candidates().len(),
+                    &ghost_information.name,
+                    &ghost_information.id,
+                    ghost_information.speed_text()
+                ),
+                if ghost_information.evidence.is_empty() {
+                    ghost_information.features.clone()
+                } else {
+                    format!(
+                        "证据: {}\n{}",
+                        ghost_information.evidence_text(),
+                        ghost_information.features
+                    )
+                },
+            ),
+            None => ("没有符合证据的鬼魂".to_string(), String::new()),
         };
 
-        if let Err(error) = listen(callback) {
-            println!("Error: {:?}", error);
+        let (width, height) = (canvas.size().x as f32, canvas.size().y as f32);
+        let padding = (layout.padding * scale) as f32;
+        let area = |top: f32, height: f32| {
+            graphics::FloatRect::new(padding, top, width - padding * 2.0, height)
+        };
+        let placements = layout.arrange(
+            height,
+            scale,
+            |block| block.visible.is_met(&state),
+            |block| texts.place(block, area(0.0, 0.0), &fonts, state.timers(), scale),
+        );
+        for placement in &placements {
+            texts.place(
+                &placement.block,
+                area(placement.top, placement.height),
+                &fonts,
+                state.timers(),
+                scale,
+            );
         }
-    });
 
-    while !window_should_close.load(Ordering::Relaxed) {
-        while let Some(event) = window.poll_event() {
-            if event == window::Event::Closed {
-                window.close()
+        if let Some(placement) = placements
+            .iter()
+            .find(|placement| placement.block.section == Section::Ghost)
+        {
+            let size = placement.block.font_size.unwrap_or(DEFAULT_FONT_SIZE) * scale;
+            let line_spacing = fonts.line_spacing(size);
+            // 名称和页码各占一行，剩下的高度用来分页显示特性
+            let lines = ((placement.height / line_spacing) as usize)
+                .saturating_sub(2)
+                .max(1);
+
+            if name != ghost_name || lines != lines_per_page {
+                texts.ghost_name.set_string(&name);
+                ghost_name = name;
+                lines_per_page = lines;
+
+                feature_lines =
+                    wrap::wrap(&features, width - padding * 2.0, |c| fonts.advance(c, size));
+                state.reduce(Command::FeaturesLaidOut {
+                    pages: feature_lines.len().div_ceil(lines_per_page),
+                });
             }
-        }
-
-        if ghost_information_should_update.load(Ordering::Relaxed) {
-            let ghost_information = &config.read().unwrap().ghosts[index.load(Ordering::Relaxed)];
 
-            text_ghost_name.set_string(&format!(
-                "{}. {} ({}) {}",
-                index.load(Ordering::Relaxed),
-                &ghost_information.name,
-                &ghost_information.id,
-                &ghost_information.speed
+            let page = state.features_page();
+            texts.ghost_features.set_string(
+                &feature_lines
+                    .chunks(lines_per_page)
+                    .nth(page)
+                    .unwrap_or_default()
+                    .join("\n"),
+            );
+            if state.features_pages() > 1 || state.is_auto_scrolling() {
+                texts.features_page.set_string(&format!(
+                    "{}第 {}/{} 页",
+                    if state.is_auto_scrolling() {
+                        "自动翻页 "
+                    } else {
+                        ""
+                    },
+                    page + 1,
+                    state.features_pages()
+                ));
+            } else {
+                texts.features_page.set_string("");
+            }
+            // 靠右对齐
+            texts.features_page.set_position(system::Vector2f::new(
+                width - padding - texts.features_page.global_bounds().width,
+                placement.top + placement.height - line_spacing,
             ));
+        }
 
-            text_ghost_features.set_string(&ghost_information.features);
-            let mut string = ghost_information.features.clone();
-
-            let mut sum = (10 * SCALE) as f32;
-            let mut byte_count = 0;
-            for char in ghost_information.features.chars() {
-                let tmp = font.glyph(char as u32, 10 * SCALE, false, 0f32).advance();
-                sum += tmp;
-                byte_count += char.len_utf8();
-
-                println!("{:?} | advance {} | byte_count {}", char, tmp, byte_count);
-                if char == '\n' {
-                    sum = (10 * SCALE) as f32;
-                } else if sum >= window.size().x as f32 {
-                    println!("Enter {sum} | {}", window.size().x);
-                    string.insert(byte_count - char.len_utf8(), '\n');
-                    byte_count += '\n'.len_utf8();
-                    sum = tmp + (10 * SCALE) as f32;
-                }
+        let timers = state.timers();
+        for (text, timer) in texts.timers.iter_mut().zip(timers.iter()) {
+            text.set_string(&timer.text());
+
+            let passed_thresholds = timer.passed_thresholds();
+            if timer.is_flashing()
+                && (timer.elapsed().as_millis() / FLASH_INTERVAL.as_millis()).is_multiple_of(2)
+            {
+                text.set_fill_color(highlight_color);
+            } else if timer.is_flashing() || passed_thresholds == 0 {
+                text.set_fill_color(text_color);
+            } else if passed_thresholds < timer.thresholds().len() {
+                text.set_fill_color(highlight_color);
+            } else {
+                text.set_fill_color(warning_color);
             }
+        }
 
-            text_ghost_features.set_string(&string);
-
-            ghost_information_should_update.store(false, Ordering::Relaxed);
+        for (text, timer) in texts.laps.iter_mut().zip(timers.iter()) {
+            if let Some(text) = text {
+                text.set_string(&timer.lap_lines().join("\n"));
+            }
         }
 
-        {
-            let stopwatch = stopwatch.read().unwrap();
-            text_timer.set_string(&format!(
-                "{:02}:{:02}",
-                stopwatch.elapsed().as_secs() / 60,
-                stopwatch.elapsed().as_secs() % 60
-            ));
+        for (text, (_, timer_index)) in texts.tips.iter_mut().zip(tips.iter()) {
+            match timer_index {
+                Some(timer_index) if timers[*timer_index].is_running() => {
+                    text.set_fill_color(highlight_color)
+                }
+                _ => text.set_fill_color(text_color),
+            }
+        }
 
-            if stopwatch.start {
-                text_tips[0].set_fill_color(TEXT_COLOR_HIGHLIGHT);
-            } else {
-                text_tips[0].set_fill_color(TEXT_COLOR);
+        let quit_confirmation = state.quit_confirmation();
+        if let Some(remaining) = quit_confirmation.remaining(now) {
+            match quit_confirmation.mode() {
+                ConfirmMode::DoublePress { .. } => texts.armed.set_string("再按一次退出"),
+                _ => texts
+                    .armed
+                    .set_string(&format!("松开取消退出 {:.1}s", remaining.as_secs_f32())),
             }
+            texts.armed.set_fill_color(warning_color);
+        } else if state.is_armed() {
+            texts.armed.set_string("热键已启用");
+            texts.armed.set_fill_color(highlight_color);
+        } else {
+            texts.armed.set_string("热键已停用");
+            texts.armed.set_fill_color(excluded_color);
         }
 
-        window.clear(graphics::Color::BLACK);
+        match state.speed() {
+            Some(estimate) => {
+                texts.speed.set_string(&format!(
+                    "脚步 {:.1}步/秒 {:.2}m/s {}{}",
+                    estimate.steps_per_second,
+                    estimate.meters_per_second,
+                    estimate.class.name(),
+                    if estimate.accelerating {
+                        " 视野加速"
+                    } else {
+                        ""
+                    }
+                ));
+                texts.speed.set_fill_color(if estimate.accelerating {
+                    highlight_color
+                } else {
+                    text_color
+                });
+            }
+            None => {
+                texts.speed.set_string("脚步测速 --");
+                texts.speed.set_fill_color(text_color);
+            }
+        }
 
-        window.draw(&text_title);
-        window.draw(&text_timer);
-        for text_tip in &text_tips {
-            window.draw(text_tip);
+        texts.candidates.set_string(&format!(
+            "候选鬼魂 {}/{}",
+            state.candidates().len(),
+            state.config().ghosts.len()
+        ));
+
+        let investigation = state.investigation();
+        texts
+            .evidence_count
+            .set_string(&format!("{}证据", investigation.evidence_count()));
+
+        for (text, evidence) in texts.evidence.iter_mut().zip(Evidence::ALL) {
+            match investigation.state(evidence) {
+                EvidenceState::Unknown => {
+                    text.set_fill_color(text_color);
+                    text.set_style(graphics::TextStyle::REGULAR);
+                }
+                EvidenceState::Confirmed => {
+                    text.set_fill_color(highlight_color);
+                    text.set_style(graphics::TextStyle::BOLD);
+                }
+                EvidenceState::Excluded => {
+                    text.set_fill_color(excluded_color);
+                    text.set_style(graphics::TextStyle::STRIKETHROUGH);
+                }
+            }
         }
 
-        window.draw(&text_ghost_name);
-        window.draw(&text_ghost_features);
+        // 在透明背景上按 alpha 混合后得到的正好是预乘透明度的颜色
+        canvas.clear(color(theme.background.premultiplied()));
 
-        unsafe {
-            SetWindowPos(h_wnd, HWND_TOPMOST, 0, 0, 0, 0, SWP_NOMOVE | SWP_NOSIZE)?;
+        for placement in &placements {
+            texts.draw(&placement.block, timers, &mut canvas, &theme, scale);
         }
 
-        window.display();
+        canvas.display();
+        let image = canvas.texture().copy_to_image().ok_or("无法读取画布内容")?;
+        overlay.present(&image)?;
     }
 
     window.close();
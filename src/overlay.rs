@@ -0,0 +1,65 @@
+// phasutils: 为Steam游戏恐鬼症设计的实用工具
+// Copyright (C) 2024  Chen Siyuan
+//
+// This program is free software: you can redistribute it and/or modify
+// it under the terms of the GNU General Public License as published by
+// the Free Software Foundation, either version 3 of the License, or
+// (at your option) any later version.
+//
+// This program is distributed in the hope that it will be useful,
+// but WITHOUT ANY WARRANTY; without even the implied warranty of
+// MERCHANTABILITY or FITNESS FOR A PARTICULAR PURPOSE.  See the
+// GNU General Public License for more details.
+//
+// You should have received a copy of the GNU General Public License
+// along with this program.  If not, see <https://www.gnu.org/licenses/>.
+
+use std::error;
+
+use phasutils::settings::Monitor;
+use sfml::{graphics, system};
+
+cfg_if::cfg_if! {
+    if #[cfg(windows)] {
+        mod win32;
+        pub use win32::Win32Overlay as PlatformOverlay;
+    } else if #[cfg(all(unix, not(target_os = "macos")))] {
+        mod x11;
+        pub use self::x11::X11Overlay as PlatformOverlay;
+    } else {
+        compile_error!("悬浮窗目前只支持 Windows 和 X11");
+    }
+}
+
+// 逐像素透明、不接收鼠标、始终置顶的悬浮窗
+pub trait OverlayWindow {
+    fn new(window: &graphics::RenderWindow) -> Result<Self, Box<dyn error::Error>>
+    where
+        Self: Sized;
+
+    // `image` 是在透明背景上绘制的画面，颜色分量已经乘过透明度
+    fn present(&mut self, image: &graphics::Image) -> Result<(), Box<dyn error::Error>>;
+
+    fn set_position(&mut self, position: system::Vector2i);
+
+    // 切换模式时调用，之后 `present` 的画面也是这个大小
+    fn set_size(&mut self, size: system::Vector2u);
+
+    fn set_visible(&mut self, visible: bool);
+
+    // 按系统的顺序列出所有显示器
+    fn monitors(&self) -> Vec<Monitor>;
+
+    // 每次唤醒时调用，只在窗口被其他窗口盖住后重新置顶
+    fn keep_on_top(&mut self) -> Result<(), Box<dyn error::Error>>;
+}
+
+// SFML 的像素是 RGBA，分层窗口和 X11 的 32 位视觉都是小端的 BGRA
+fn to_bgra(image: &graphics::Image) -> Vec<u8> {
+    let mut pixels = image.pixel_data().to_vec();
+    for pixel in pixels.chunks_exact_mut(4) {
+        pixel.swap(0, 2);
+    }
+
+    pixels
+}
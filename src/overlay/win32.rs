@@ -0,0 +1,241 @@
+// phasutils: 为Steam游戏恐鬼症设计的实用工具
+// Copyright (C) 2024  Chen Siyuan
+//
+// This program is free software: you can redistribute it and/or modify
+// it under the terms of the GNU General Public License as published by
+// the Free Software Foundation, either version 3 of the License, or
+// (at your option) any later version.
+//
+// This program is distributed in the hope that it will be useful,
+// but WITHOUT ANY WARRANTY; without even the implied warranty of
+// MERCHANTABILITY or FITNESS FOR A PARTICULAR PURPOSE.  See the
+// GNU General Public License for more details.
+//
+// You should have received a copy of the GNU General Public License
+// along with this program.  If not, see <https://www.gnu.org/licenses/>.
+
+use std::{error, mem, ptr};
+
+use phasutils::settings::Monitor;
+use sfml::{graphics, system};
+use windows::Win32::Foundation::{BOOL, COLORREF, HANDLE, HWND, LPARAM, POINT, RECT, SIZE};
+use windows::Win32::Graphics::Gdi::{
+    CreateCompatibleDC, CreateDIBSection, DeleteDC, DeleteObject, EnumDisplayMonitors, GetDC,
+    IntersectRect, ReleaseDC, SelectObject, AC_SRC_ALPHA, AC_SRC_OVER, BITMAPINFO,
+    BITMAPINFOHEADER, BI_RGB, BLENDFUNCTION, DIB_RGB_COLORS, HDC, HGDIOBJ, HMONITOR,
+};
+use windows::Win32::UI::WindowsAndMessaging::{
+    GetWindow, GetWindowLongW, GetWindowRect, IsWindowVisible, SetWindowLongW, SetWindowPos,
+    ShowWindow, UpdateLayeredWindow, GWL_EXSTYLE, GW_HWNDPREV, HWND_TOPMOST, SWP_NOACTIVATE,
+    SWP_NOMOVE, SWP_NOSIZE, SWP_NOZORDER, SW_HIDE, SW_SHOWNOACTIVATE, ULW_ALPHA, WS_EX_LAYERED,
+    WS_EX_TOPMOST, WS_EX_TRANSPARENT,
+};
+
+use super::OverlayWindow;
+
+unsafe extern "system" fn enum_monitor(
+    _monitor: HMONITOR,
+    _hdc: HDC,
+    rect: *mut RECT,
+    data: LPARAM,
+) -> BOOL {
+    let monitors = &mut *(data.0 as *mut Vec<Monitor>);
+    let rect = &*rect;
+    monitors.push(Monitor {
+        x: rect.left,
+        y: rect.top,
+        width: (rect.right - rect.left) as u32,
+        height: (rect.bottom - rect.top) as u32,
+    });
+
+    true.into()
+}
+
+fn is_topmost(h_wnd: HWND) -> bool {
+    unsafe { GetWindowLongW(h_wnd, GWL_EXSTYLE) & WS_EX_TOPMOST.0 as i32 != 0 }
+}
+
+pub struct Win32Overlay {
+    h_wnd: HWND,
+}
+
+impl Win32Overlay {
+    // 沿着 z 序向上查找，只有可见的置顶窗口与悬浮窗重叠时才算被盖住
+    unsafe fn is_covered(&self) -> bool {
+        let mut rect = RECT::default();
+        if !is_topmost(self.h_wnd) || GetWindowRect(self.h_wnd, &mut rect).is_err() {
+            return true;
+        }
+
+        let mut above = GetWindow(self.h_wnd, GW_HWNDPREV);
+        while above.0 != 0 {
+            let mut other = RECT::default();
+            let mut overlap = RECT::default();
+            if IsWindowVisible(above).as_bool()
+                && is_topmost(above)
+                && GetWindowRect(above, &mut other).is_ok()
+                && IntersectRect(&mut overlap, &rect, &other).as_bool()
+            {
+                return true;
+            }
+            above = GetWindow(above, GW_HWNDPREV);
+        }
+
+        false
+    }
+
+    unsafe fn update_layered_window(
+        &self,
+        screen: HDC,
+        hdc: HDC,
+        image: &graphics::Image,
+    ) -> windows::core::Result<()> {
+        let size = image.size();
+        let pixels = super::to_bgra(image);
+
+        let mut info: BITMAPINFO = mem::zeroed();
+        info.bmiHeader = BITMAPINFOHEADER {
+            biSize: mem::size_of::<BITMAPINFOHEADER>() as u32,
+            biWidth: size.x as i32,
+            // 高度为负数时位图从上到下存储，与 SFML 的顺序一致
+            biHeight: -(size.y as i32),
+            biPlanes: 1,
+            biBitCount: 32,
+            biCompression: BI_RGB.0,
+            ..Default::default()
+        };
+        let mut bits = ptr::null_mut();
+        let bitmap = CreateDIBSection(hdc, &info, DIB_RGB_COLORS, &mut bits, HANDLE::default(), 0)?;
+        ptr::copy_nonoverlapping(pixels.as_ptr(), bits as *mut u8, pixels.len());
+        let old = SelectObject(hdc, HGDIOBJ(bitmap.0));
+
+        // 按每个像素的透明度混合，取代原来的色键
+        let blend = BLENDFUNCTION {
+            BlendOp: AC_SRC_OVER as u8,
+            BlendFlags: 0,
+            SourceConstantAlpha: 255,
+            AlphaFormat: AC_SRC_ALPHA as u8,
+        };
+        let result = UpdateLayeredWindow(
+            self.h_wnd,
+            screen,
+            None,
+            Some(&SIZE {
+                cx: size.x as i32,
+                cy: size.y as i32,
+            }),
+            hdc,
+            Some(&POINT::default()),
+            COLORREF(0),
+            Some(&blend),
+            ULW_ALPHA,
+        );
+
+        SelectObject(hdc, old);
+        let _ = DeleteObject(HGDIOBJ(bitmap.0));
+        result
+    }
+}
+
+impl OverlayWindow for Win32Overlay {
+    fn new(window: &graphics::RenderWindow) -> Result<Self, Box<dyn error::Error>> {
+        let h_wnd = HWND(window.system_handle() as isize);
+
+        // 分层窗口加上 `WS_EX_TRANSPARENT` 后鼠标点击会穿透到游戏，
+        // 画面由 `UpdateLayeredWindow` 提供，SFML 自己的绘制不会显示
+        unsafe {
+            SetWindowLongW(
+                h_wnd,
+                GWL_EXSTYLE,
+                GetWindowLongW(h_wnd, GWL_EXSTYLE)
+                    | WS_EX_LAYERED.0 as i32
+                    | WS_EX_TRANSPARENT.0 as i32,
+            );
+        }
+
+        Ok(Win32Overlay { h_wnd })
+    }
+
+    fn present(&mut self, image: &graphics::Image) -> Result<(), Box<dyn error::Error>> {
+        unsafe {
+            let screen = GetDC(HWND::default());
+            let hdc = CreateCompatibleDC(screen);
+            let result = self.update_layered_window(screen, hdc, image);
+            let _ = DeleteDC(hdc);
+            ReleaseDC(HWND::default(), screen);
+
+            Ok(result?)
+        }
+    }
+
+    fn set_position(&mut self, position: system::Vector2i) {
+        unsafe {
+            let _ = SetWindowPos(
+                self.h_wnd,
+                HWND::default(),
+                position.x,
+                position.y,
+                0,
+                0,
+                SWP_NOSIZE | SWP_NOZORDER | SWP_NOACTIVATE,
+            );
+        }
+    }
+
+    fn set_size(&mut self, size: system::Vector2u) {
+        unsafe {
+            let _ = SetWindowPos(
+                self.h_wnd,
+                HWND::default(),
+                0,
+                0,
+                size.x as i32,
+                size.y as i32,
+                SWP_NOMOVE | SWP_NOZORDER | SWP_NOACTIVATE,
+            );
+        }
+    }
+
+    fn set_visible(&mut self, visible: bool) {
+        unsafe {
+            let _ = ShowWindow(
+                self.h_wnd,
+                if visible { SW_SHOWNOACTIVATE } else { SW_HIDE },
+            );
+        }
+    }
+
+    fn monitors(&self) -> Vec<Monitor> {
+        let mut monitors: Vec<Monitor> = vec![];
+        unsafe {
+            let _ = EnumDisplayMonitors(
+                HDC::default(),
+                None,
+                Some(enum_monitor),
+                LPARAM(&mut monitors as *mut _ as isize),
+            );
+        }
+
+        monitors
+    }
+
+    fn keep_on_top(&mut self) -> Result<(), Box<dyn error::Error>> {
+        unsafe {
+            if !self.is_covered() {
+                return Ok(());
+            }
+
+            SetWindowPos(
+                self.h_wnd,
+                HWND_TOPMOST,
+                0,
+                0,
+                0,
+                0,
+                SWP_NOMOVE | SWP_NOSIZE | SWP_NOACTIVATE,
+            )?;
+        }
+
+        Ok(())
+    }
+}
@@ -0,0 +1,273 @@
+// phasutils: 为Steam游戏恐鬼症设计的实用工具
+// Copyright (C) 2024  Chen Siyuan
+//
+// This program is free software: you can redistribute it and/or modify
+// it under the terms of the GNU General Public License as published by
+// the Free Software Foundation, either version 3 of the License, or
+// (at your option) any later version.
+//
+// This program is distributed in the hope that it will be useful,
+// but WITHOUT ANY WARRANTY; without even the implied warranty of
+// MERCHANTABILITY or FITNESS FOR A PARTICULAR PURPOSE.  See the
+// GNU General Public License for more details.
+//
+// You should have received a copy of the GNU General Public License
+// along with this program.  If not, see <https://www.gnu.org/licenses/>.
+
+use std::ffi::CString;
+use std::{error, mem, ptr, slice};
+
+use log::warn;
+use phasutils::settings::Monitor;
+use sfml::graphics::{self, RenderTarget};
+use sfml::system;
+use x11::{xfixes, xlib, xrandr};
+
+use super::OverlayWindow;
+
+// X11/extensions/shape.h
+const SHAPE_INPUT: i32 = 2;
+
+// SFML 创建的窗口只有 24 位色深，这里另建一个 32 位 ARGB 视觉的窗口来显示画面，
+// SFML 的窗口保持隐藏，只用来创建 OpenGL 上下文
+pub struct X11Overlay {
+    display: *mut xlib::Display,
+    window: xlib::Window,
+    visual: *mut xlib::Visual,
+    colormap: xlib::Colormap,
+    gc: xlib::GC,
+}
+
+impl X11Overlay {
+    fn atom(&self, name: &str) -> xlib::Atom {
+        let name = CString::new(name).unwrap();
+        unsafe { xlib::XInternAtom(self.display, name.as_ptr(), xlib::False) }
+    }
+
+    fn query_tree(&self, window: xlib::Window) -> (xlib::Window, Vec<xlib::Window>) {
+        let (mut root, mut parent) = (0, 0);
+        let mut children = ptr::null_mut();
+        let mut count = 0;
+        unsafe {
+            if xlib::XQueryTree(
+                self.display,
+                window,
+                &mut root,
+                &mut parent,
+                &mut children,
+                &mut count,
+            ) == 0
+            {
+                return (0, vec![]);
+            }
+            if children.is_null() {
+                return (parent, vec![]);
+            }
+
+            let list = slice::from_raw_parts(children, count as usize).to_vec();
+            xlib::XFree(children as *mut _);
+            (parent, list)
+        }
+    }
+
+    // 窗口管理器会把窗口放进自己的边框窗口里，比较层级时要用最外层的窗口
+    fn is_on_top(&self) -> bool {
+        let root = unsafe { xlib::XDefaultRootWindow(self.display) };
+        let mut top_level = self.window;
+        loop {
+            match self.query_tree(top_level) {
+                (0, _) => return true,
+                (parent, _) if parent == root => break,
+                (parent, _) => top_level = parent,
+            }
+        }
+
+        // `XQueryTree` 按从下到上的顺序返回子窗口
+        self.query_tree(root).1.last() == Some(&top_level)
+    }
+}
+
+impl OverlayWindow for X11Overlay {
+    fn new(window: &graphics::RenderWindow) -> Result<Self, Box<dyn error::Error>> {
+        // SFML 不公开它的连接，窗口 ID 在同一个 X 服务器上通用
+        let display = unsafe { xlib::XOpenDisplay(ptr::null()) };
+        if display.is_null() {
+            return Err("无法连接 X 服务器".into());
+        }
+
+        unsafe {
+            let screen = xlib::XDefaultScreen(display);
+            let root = xlib::XRootWindow(display, screen);
+            let mut info: xlib::XVisualInfo = mem::zeroed();
+            if xlib::XMatchVisualInfo(display, screen, 32, xlib::TrueColor, &mut info) == 0 {
+                xlib::XCloseDisplay(display);
+                return Err("X 服务器不支持 32 位 ARGB 视觉".into());
+            }
+
+            // 绕过窗口管理器，不加边框也不会被挪动，置顶由 `keep_on_top` 负责；
+            // 透明背景让窗口在第一帧之前也不可见
+            let colormap = xlib::XCreateColormap(display, root, info.visual, xlib::AllocNone);
+            let mut attributes: xlib::XSetWindowAttributes = mem::zeroed();
+            attributes.colormap = colormap;
+            attributes.background_pixel = 0;
+            attributes.border_pixel = 0;
+            attributes.override_redirect = xlib::True;
+            let size = window.size();
+            let overlay_window = xlib::XCreateWindow(
+                display,
+                root,
+                0,
+                0,
+                size.x,
+                size.y,
+                0,
+                info.depth,
+                xlib::InputOutput as u32,
+                info.visual,
+                xlib::CWColormap
+                    | xlib::CWBackPixel
+                    | xlib::CWBorderPixel
+                    | xlib::CWOverrideRedirect,
+                &mut attributes,
+            );
+
+            let overlay = X11Overlay {
+                display,
+                window: overlay_window,
+                visual: info.visual,
+                colormap,
+                gc: xlib::XCreateGC(display, overlay_window, 0, ptr::null_mut()),
+            };
+
+            // 没有合成管理器时透明度会被忽略，半透明的部分显示为黑色
+            let compositor = overlay.atom(&format!("_NET_WM_CM_S{}", screen));
+            if xlib::XGetSelectionOwner(display, compositor) == 0 {
+                warn!("没有运行合成管理器，悬浮窗的透明部分会显示为黑色");
+            }
+
+            // 空的输入区域让鼠标点击穿透到游戏
+            let region = xfixes::XFixesCreateRegion(display, ptr::null_mut(), 0);
+            xfixes::XFixesSetWindowShapeRegion(display, overlay.window, SHAPE_INPUT, 0, 0, region);
+            xfixes::XFixesDestroyRegion(display, region);
+
+            xlib::XUnmapWindow(display, window.system_handle());
+            xlib::XMapRaised(display, overlay.window);
+            xlib::XFlush(display);
+
+            Ok(overlay)
+        }
+    }
+
+    fn present(&mut self, image: &graphics::Image) -> Result<(), Box<dyn error::Error>> {
+        let size = image.size();
+        let mut pixels = super::to_bgra(image);
+
+        unsafe {
+            let ximage = xlib::XCreateImage(
+                self.display,
+                self.visual,
+                32,
+                xlib::ZPixmap,
+                0,
+                pixels.as_mut_ptr() as *mut _,
+                size.x,
+                size.y,
+                32,
+                0,
+            );
+            if ximage.is_null() {
+                return Err("无法创建 XImage".into());
+            }
+
+            xlib::XPutImage(
+                self.display,
+                self.window,
+                self.gc,
+                ximage,
+                0,
+                0,
+                0,
+                0,
+                size.x,
+                size.y,
+            );
+            // 像素数据由 `pixels` 释放，这里只释放结构体
+            xlib::XFree(ximage as *mut _);
+            xlib::XFlush(self.display);
+        }
+
+        Ok(())
+    }
+
+    fn set_position(&mut self, position: system::Vector2i) {
+        unsafe {
+            xlib::XMoveWindow(self.display, self.window, position.x, position.y);
+            xlib::XFlush(self.display);
+        }
+    }
+
+    fn set_size(&mut self, size: system::Vector2u) {
+        unsafe {
+            xlib::XResizeWindow(self.display, self.window, size.x, size.y);
+            xlib::XFlush(self.display);
+        }
+    }
+
+    fn set_visible(&mut self, visible: bool) {
+        unsafe {
+            if visible {
+                xlib::XMapRaised(self.display, self.window);
+            } else {
+                xlib::XUnmapWindow(self.display, self.window);
+            }
+            xlib::XFlush(self.display);
+        }
+    }
+
+    fn monitors(&self) -> Vec<Monitor> {
+        unsafe {
+            let root = xlib::XDefaultRootWindow(self.display);
+            let mut count = 0;
+            let list = xrandr::XRRGetMonitors(self.display, root, xlib::True, &mut count);
+            if list.is_null() {
+                return vec![];
+            }
+
+            let monitors = slice::from_raw_parts(list, count.max(0) as usize)
+                .iter()
+                .map(|monitor| Monitor {
+                    x: monitor.x,
+                    y: monitor.y,
+                    width: monitor.width as u32,
+                    height: monitor.height as u32,
+                })
+                .collect();
+            xrandr::XRRFreeMonitors(list);
+            monitors
+        }
+    }
+
+    fn keep_on_top(&mut self) -> Result<(), Box<dyn error::Error>> {
+        if self.is_on_top() {
+            return Ok(());
+        }
+
+        unsafe {
+            xlib::XRaiseWindow(self.display, self.window);
+            xlib::XFlush(self.display);
+        }
+
+        Ok(())
+    }
+}
+
+impl Drop for X11Overlay {
+    fn drop(&mut self) {
+        unsafe {
+            xlib::XFreeGC(self.display, self.gc);
+            xlib::XDestroyWindow(self.display, self.window);
+            xlib::XFreeColormap(self.display, self.colormap);
+            xlib::XCloseDisplay(self.display);
+        }
+    }
+}
@@ -0,0 +1,131 @@
+// phasutils: 为Steam游戏恐鬼症设计的实用工具
+// Copyright (C) 2024  Chen Siyuan
+//
+// This program is free software: you can redistribute it and/or modify
+// it under the terms of the GNU General Public License as published by
+// the Free Software Foundation, either version 3 of the License, or
+// (at your option) any later version.
+//
+// This program is distributed in the hope that it will be useful,
+// but WITHOUT ANY WARRANTY; without even the implied warranty of
+// MERCHANTABILITY or FITNESS FOR A PARTICULAR PURPOSE.  See the
+// GNU General Public License for more details.
+//
+// You should have received a copy of the GNU General Public License
+// along with this program.  If not, see <https://www.gnu.org/licenses/>.
+
+use std::time;
+
+use crate::app::AppState;
+
+// 唤醒时间略晚于显示变化，避免醒来时计时器还差一点才进位
+const DEADLINE_SLACK: time::Duration = time::Duration::from_millis(1);
+
+// 只在状态变化或计时器显示进位时重绘，其余时间按空闲帧率检查窗口事件
+pub struct RedrawScheduler {
+    idle_interval: time::Duration,
+    // 上一次重绘时的状态版本，`None` 表示需要重绘
+    revision: Option<u64>,
+    deadline: Option<time::Instant>,
+}
+
+impl RedrawScheduler {
+    pub fn new(idle_frame_rate: u32) -> RedrawScheduler {
+        RedrawScheduler {
+            idle_interval: time::Duration::from_secs(1) / idle_frame_rate.max(1),
+            revision: None,
+            deadline: None,
+        }
+    }
+
+    // 窗口事件等界面自身的原因要求重绘
+    pub fn invalidate(&mut self) {
+        self.revision = None;
+    }
+
+    // 最多等待多久就要再次检查
+    pub fn timeout(&self, now: time::Instant) -> time::Duration {
+        match self.deadline {
+            Some(deadline) => self
+                .idle_interval
+                .min(deadline.saturating_duration_since(now)),
+            None => self.idle_interval,
+        }
+    }
+
+    pub fn should_redraw(&mut self, state: &AppState, now: time::Instant) -> bool {
+        let due = self.revision != Some(state.revision())
+            || self.deadline.is_some_and(|deadline| now >= deadline);
+        if due {
+            self.revision = Some(state.revision());
+            self.deadline = state
+                .next_change(now)
+                .map(|next_change| now + next_change + DEADLINE_SLACK);
+        }
+
+        due
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::app::Command;
+    use crate::config::{Config, TimerConfig};
+    use crate::input::{Bindings, Trigger};
+    use crate::source::InputEvent;
+
+    fn state(show_tenths: bool) -> AppState {
+        AppState::new(Config {
+            version: 2,
+            bindings: Bindings::default(),
+            timers: vec![TimerConfig {
+                id: "main".to_string(),
+                start: Some(rdev::Key::Num1.into()),
+                show_tenths,
+                ..Default::default()
+            }],
+            presets: vec![],
+            idle_frame_rate: 10,
+            auto_scroll_seconds: 8,
+            ghosts: vec![],
+        })
+    }
+
+    fn press(state: &mut AppState, key: rdev::Key, now: time::Instant) {
+        state.reduce(Command::Input(InputEvent::Press(Trigger::Key(key)), now));
+        state.reduce(Command::Input(InputEvent::Release(Trigger::Key(key)), now));
+    }
+
+    #[test]
+    fn redraws_only_after_changes() {
+        let mut state = state(false);
+        let mut redraw = RedrawScheduler::new(10);
+        let now = time::Instant::now();
+
+        assert!(redraw.should_redraw(&state, now));
+        assert!(!redraw.should_redraw(&state, now));
+        assert_eq!(redraw.timeout(now), time::Duration::from_millis(100));
+
+        press(&mut state, rdev::Key::F1, now);
+        assert!(redraw.should_redraw(&state, now));
+        assert!(!redraw.should_redraw(&state, now));
+
+        redraw.invalidate();
+        assert!(redraw.should_redraw(&state, now));
+    }
+
+    #[test]
+    fn running_timer_sets_a_deadline() {
+        let mut state = state(true);
+        let mut redraw = RedrawScheduler::new(1);
+        let now = time::Instant::now();
+
+        press(&mut state, rdev::Key::Num1, now);
+        assert!(redraw.should_redraw(&state, now));
+        // 显示十分之一秒时不等空闲帧
+        let timeout = redraw.timeout(now);
+        assert!(timeout <= time::Duration::from_millis(100) + DEADLINE_SLACK);
+        assert!(redraw.should_redraw(&state, now + timeout));
+    }
+}
@@ -0,0 +1,373 @@
+// phasutils: 为Steam游戏恐鬼症设计的实用工具
+// Copyright (C) 2024  Chen Siyuan
+//
+// This program is free software: you can redistribute it and/or modify
+// it under the terms of the GNU General Public License as published by
+// the Free Software Foundation, either version 3 of the License, or
+// (at your option) any later version.
+//
+// This program is distributed in the hope that it will be useful,
+// but WITHOUT ANY WARRANTY; without even the implied warranty of
+// MERCHANTABILITY or FITNESS FOR A PARTICULAR PURPOSE.  See the
+// GNU General Public License for more details.
+//
+// You should have received a copy of the GNU General Public License
+// along with this program.  If not, see <https://www.gnu.org/licenses/>.
+
+use std::collections::HashMap;
+use std::{error, fs, io, path};
+
+use log::warn;
+use serde::{Deserialize, Serialize};
+
+use crate::layout::Layout;
+use crate::theme::{Theme, DEFAULT_THEME};
+
+// 窗口位置等个人设置，与共享的 config.json 分开保存在同一个目录
+pub const SETTINGS_FILE: &str = "settings.json";
+
+#[derive(Serialize, Deserialize, Debug, Clone, Copy, PartialEq, Eq, Default)]
+#[serde(rename_all = "snake_case")]
+pub enum Anchor {
+    #[default]
+    TopLeft,
+    TopRight,
+    BottomLeft,
+    BottomRight,
+}
+
+// 显示器在整个桌面中的位置和大小
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct Monitor {
+    pub x: i32,
+    pub y: i32,
+    pub width: u32,
+    pub height: u32,
+}
+
+impl Monitor {
+    fn contains(&self, (x, y): (i32, i32)) -> bool {
+        x >= self.x
+            && y >= self.y
+            && x < self.x + self.width as i32
+            && y < self.y + self.height as i32
+    }
+}
+
+#[derive(Serialize, Deserialize, Debug, Clone, PartialEq)]
+#[serde(default)]
+pub struct WindowSettings {
+    // 所有尺寸都会乘以 `scale`
+    pub scale: u32,
+    pub width: u32,
+    pub height: u32,
+    // 偏移从 `anchor` 所在的角向显示器内部计算，单位是实际像素
+    pub anchor: Anchor,
+    pub offset_x: i32,
+    pub offset_y: i32,
+    pub monitor: usize,
+}
+
+impl Default for WindowSettings {
+    fn default() -> Self {
+        WindowSettings {
+            scale: 3,
+            width: 200,
+            height: 300,
+            anchor: Anchor::TopLeft,
+            offset_x: 0,
+            offset_y: 0,
+            monitor: 0,
+        }
+    }
+}
+
+impl WindowSettings {
+    // 缩放或大小为 0 时无法创建画布
+    fn clamp(&mut self) {
+        if self.scale == 0 || self.width == 0 || self.height == 0 {
+            warn!(
+                "窗口缩放和大小不能为 0: scale {}, {}x{}",
+                self.scale, self.width, self.height
+            );
+            self.scale = self.scale.max(1);
+            self.width = self.width.max(1);
+            self.height = self.height.max(1);
+        }
+    }
+
+    // 实际像素大小
+    pub fn size(&self) -> (u32, u32) {
+        (self.width * self.scale, self.height * self.scale)
+    }
+
+    pub fn position(&self, monitor: &Monitor) -> (i32, i32) {
+        let (width, height) = self.size();
+        let left = monitor.x + self.offset_x;
+        let top = monitor.y + self.offset_y;
+        let right = monitor.x + monitor.width as i32 - width as i32 - self.offset_x;
+        let bottom = monitor.y + monitor.height as i32 - height as i32 - self.offset_y;
+
+        match self.anchor {
+            Anchor::TopLeft => (left, top),
+            Anchor::TopRight => (right, top),
+            Anchor::BottomLeft => (left, bottom),
+            Anchor::BottomRight => (right, bottom),
+        }
+    }
+
+    // 拖动后按窗口中心所在的显示器重新计算偏移，保持原来的锚点
+    pub fn move_to(&mut self, monitors: &[Monitor], (x, y): (i32, i32)) {
+        let (width, height) = self.size();
+        let center = (x + width as i32 / 2, y + height as i32 / 2);
+        if let Some(index) = monitors.iter().position(|monitor| monitor.contains(center)) {
+            self.monitor = index;
+        }
+        let Some(monitor) = monitors.get(self.monitor) else {
+            self.offset_x = x;
+            self.offset_y = y;
+            return;
+        };
+
+        self.offset_x = match self.anchor {
+            Anchor::TopLeft | Anchor::BottomLeft => x - monitor.x,
+            Anchor::TopRight | Anchor::BottomRight => {
+                monitor.x + monitor.width as i32 - width as i32 - x
+            }
+        };
+        self.offset_y = match self.anchor {
+            Anchor::TopLeft | Anchor::TopRight => y - monitor.y,
+            Anchor::BottomLeft | Anchor::BottomRight => {
+                monitor.y + monitor.height as i32 - height as i32 - y
+            }
+        };
+    }
+}
+
+#[derive(Serialize, Deserialize, Debug, Clone)]
+#[serde(default)]
+pub struct Settings {
+    pub window: WindowSettings,
+    // 内置主题或 `themes` 中自定义主题的名称
+    pub theme: String,
+    pub themes: HashMap<String, Theme>,
+    // 按顺序查找字形的字体文件，相对路径从配置目录开始，内置字体总是排在最后
+    pub fonts: Vec<path::PathBuf>,
+    // 各组文字的排列顺序、字号和显示条件
+    pub layout: Layout,
+    // 精简模式有单独的窗口大小、位置和布局
+    pub compact_window: WindowSettings,
+    pub compact_layout: Layout,
+}
+
+impl Default for Settings {
+    fn default() -> Self {
+        Settings {
+            window: WindowSettings::default(),
+            theme: DEFAULT_THEME.to_string(),
+            themes: HashMap::new(),
+            fonts: vec![],
+            layout: Layout::default(),
+            compact_window: WindowSettings {
+                width: 130,
+                height: 100,
+                anchor: Anchor::TopRight,
+                ..Default::default()
+            },
+            compact_layout: Layout::compact(),
+        }
+    }
+}
+
+impl Settings {
+    pub fn window(&self, compact: bool) -> &WindowSettings {
+        if compact {
+            &self.compact_window
+        } else {
+            &self.window
+        }
+    }
+
+    pub fn window_mut(&mut self, compact: bool) -> &mut WindowSettings {
+        if compact {
+            &mut self.compact_window
+        } else {
+            &mut self.window
+        }
+    }
+
+    pub fn layout(&self, compact: bool) -> &Layout {
+        if compact {
+            &self.compact_layout
+        } else {
+            &self.layout
+        }
+    }
+
+    // 自定义主题可以覆盖同名的内置主题
+    pub fn theme(&self) -> Theme {
+        self.themes
+            .get(&self.theme)
+            .cloned()
+            .or_else(|| Theme::builtin(&self.theme))
+            .unwrap_or_else(|| {
+                warn!("找不到主题 {}，使用默认主题", self.theme);
+                Theme::default()
+            })
+    }
+
+    // 文件不存在时使用默认设置
+    pub fn load<P: AsRef<path::Path>>(path: P) -> Result<Settings, Box<dyn error::Error>> {
+        let mut settings: Settings = match fs::read_to_string(path) {
+            Ok(text) => serde_json::from_str(&text)?,
+            Err(err) if err.kind() == io::ErrorKind::NotFound => Settings::default(),
+            Err(err) => return Err(err.into()),
+        };
+        settings.window.clamp();
+        settings.compact_window.clamp();
+        Ok(settings)
+    }
+
+    // 只写入拖动后的窗口位置，文件中的其他设置保持原样，没写的设置继续使用默认值
+    pub fn save_window<P: AsRef<path::Path>>(
+        &self,
+        path: P,
+        compact: bool,
+    ) -> Result<(), Box<dyn error::Error>> {
+        let path = path.as_ref();
+        let mut value = match fs::read_to_string(path) {
+            Ok(text) => serde_json::from_str(&text)?,
+            Err(err) if err.kind() == io::ErrorKind::NotFound => serde_json::json!({}),
+            Err(err) => return Err(err.into()),
+        };
+        let key = if compact { "compact_window" } else { "window" };
+        value
+            .as_object_mut()
+            .ok_or("设置文件不是 JSON 对象")?
+            .insert(key.to_string(), serde_json::to_value(self.window(compact))?);
+        fs::write(path, serde_json::to_string_pretty(&value)? + "\n")?;
+        Ok(())
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    const MONITORS: [Monitor; 2] = [
+        Monitor {
+            x: 0,
+            y: 0,
+            width: 2560,
+            height: 1440,
+        },
+        Monitor {
+            x: 2560,
+            y: 0,
+            width: 1920,
+            height: 1080,
+        },
+    ];
+
+    fn settings(anchor: Anchor) -> WindowSettings {
+        WindowSettings {
+            scale: 2,
+            anchor,
+            offset_x: 10,
+            offset_y: 20,
+            ..Default::default()
+        }
+    }
+
+    #[test]
+    fn position_from_anchor() {
+        let monitor = &MONITORS[0];
+        assert_eq!(settings(Anchor::TopLeft).position(monitor), (10, 20));
+        assert_eq!(settings(Anchor::TopRight).position(monitor), (2150, 20));
+        assert_eq!(settings(Anchor::BottomLeft).position(monitor), (10, 820));
+        assert_eq!(settings(Anchor::BottomRight).position(monitor), (2150, 820));
+        assert_eq!(
+            settings(Anchor::TopRight).position(&MONITORS[1]),
+            (2560 + 1510, 20)
+        );
+    }
+
+    #[test]
+    fn move_to_keeps_anchor() {
+        for anchor in [
+            Anchor::TopLeft,
+            Anchor::TopRight,
+            Anchor::BottomLeft,
+            Anchor::BottomRight,
+        ] {
+            let mut settings = settings(anchor);
+            settings.move_to(&MONITORS, (1000, 500));
+            assert_eq!(settings.monitor, 0);
+            assert_eq!(settings.position(&MONITORS[0]), (1000, 500));
+        }
+    }
+
+    #[test]
+    fn move_to_other_monitor() {
+        let mut settings = settings(Anchor::TopRight);
+        settings.move_to(&MONITORS, (3000, 100));
+        assert_eq!(settings.monitor, 1);
+        assert_eq!((settings.offset_x, settings.offset_y), (1080, 100));
+        assert_eq!(settings.position(&MONITORS[1]), (3000, 100));
+    }
+
+    #[test]
+    fn zero_scale_is_clamped() {
+        let path =
+            std::env::temp_dir().join(format!("phasutils-scale-{}.json", std::process::id()));
+        fs::write(
+            &path,
+            r#"{"window": {"scale": 0}, "compact_window": {"width": 0}}"#,
+        )
+        .unwrap();
+        let settings = Settings::load(&path).unwrap();
+        fs::remove_file(&path).unwrap();
+        assert_eq!(settings.window.size(), (200, 300));
+        assert_eq!(settings.compact_window.size(), (3, 900));
+    }
+
+    #[test]
+    fn save_window_keeps_other_settings() {
+        let path = std::env::temp_dir().join(format!("phasutils-save-{}.json", std::process::id()));
+        fs::write(&path, r#"{"theme": "red_green", "window": {"scale": 2}}"#).unwrap();
+        let mut settings = Settings::load(&path).unwrap();
+        settings.window.move_to(&MONITORS, (1000, 500));
+        settings.save_window(&path, false).unwrap();
+
+        let text = fs::read_to_string(&path).unwrap();
+        fs::remove_file(&path).unwrap();
+        let value: serde_json::Value = serde_json::from_str(&text).unwrap();
+        assert_eq!(value["theme"], "red_green");
+        assert_eq!(value["window"]["scale"], 2);
+        assert_eq!(value["window"]["offset_x"], 1000);
+        // 没有写过的设置不会被默认值固定下来
+        assert!(value.get("layout").is_none());
+        assert!(value.get("compact_window").is_none());
+    }
+
+    #[test]
+    fn custom_theme_overrides_builtin() {
+        let mut settings = Settings::default();
+        assert_eq!(settings.theme(), Theme::default());
+
+        settings.theme = "red_green".to_string();
+        assert_eq!(settings.theme(), Theme::builtin("red_green").unwrap());
+
+        let custom = Theme {
+            outline_thickness: 2.0,
+            ..Theme::default()
+        };
+        settings
+            .themes
+            .insert("red_green".to_string(), custom.clone());
+        assert_eq!(settings.theme(), custom);
+
+        settings.theme = "missing".to_string();
+        assert_eq!(settings.theme(), Theme::default());
+    }
+}
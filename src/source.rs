@@ -0,0 +1,109 @@
+// phasutils: 为Steam游戏恐鬼症设计的实用工具
+// Copyright (C) 2024  Chen Siyuan
+//
+// This program is free software: you can redistribute it and/or modify
+// it under the terms of the GNU General Public License as published by
+// the Free Software Foundation, either version 3 of the License, or
+// (at your option) any later version.
+//
+// This program is distributed in the hope that it will be useful,
+// but WITHOUT ANY WARRANTY; without even the implied warranty of
+// MERCHANTABILITY or FITNESS FOR A PARTICULAR PURPOSE.  See the
+// GNU General Public License for more details.
+//
+// You should have received a copy of the GNU General Public License
+// along with this program.  If not, see <https://www.gnu.org/licenses/>.
+
+use std::sync::mpsc;
+use std::{thread, time};
+
+use log::{error, warn};
+
+use crate::app::Command;
+use crate::input::Trigger;
+
+// 等待手柄事件的最长时间，没有手柄时线程也只是偶尔醒来
+const GAMEPAD_WAIT_TIMEOUT: time::Duration = time::Duration::from_secs(1);
+
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub enum InputEvent {
+    Press(Trigger),
+    Release(Trigger),
+}
+
+// rdev 直接报告系统的按键编号，Windows 的侧键是 XBUTTON1 和 XBUTTON2
+#[cfg(windows)]
+fn mouse_button(button: rdev::Button) -> rdev::Button {
+    use crate::input::{MOUSE_BACK, MOUSE_FORWARD};
+
+    match button {
+        rdev::Button::Unknown(1) => MOUSE_BACK,
+        rdev::Button::Unknown(2) => MOUSE_FORWARD,
+        button => button,
+    }
+}
+
+// X11 的侧键是按键 8 和 9
+#[cfg(not(windows))]
+fn mouse_button(button: rdev::Button) -> rdev::Button {
+    button
+}
+
+// 全局监听键盘和鼠标按键，鼠标移动和滚轮不转发
+pub fn spawn_keyboard_mouse(sender: mpsc::Sender<Command>) {
+    thread::spawn(move || {
+        let callback = move |event: rdev::Event| {
+            let event = match event.event_type {
+                rdev::EventType::KeyPress(key) => InputEvent::Press(Trigger::Key(key)),
+                rdev::EventType::KeyRelease(key) => InputEvent::Release(Trigger::Key(key)),
+                rdev::EventType::ButtonPress(button) => {
+                    InputEvent::Press(Trigger::Mouse(mouse_button(button)))
+                }
+                rdev::EventType::ButtonRelease(button) => {
+                    InputEvent::Release(Trigger::Mouse(mouse_button(button)))
+                }
+                _ => return,
+            };
+            let _ = sender.send(Command::Input(event, time::Instant::now()));
+        };
+
+        if let Err(err) = rdev::listen(callback) {
+            error!("无法监听键盘和鼠标: {:?}", err);
+        }
+    });
+}
+
+// 没有手柄或初始化失败时只打印警告
+pub fn spawn_gamepad(sender: mpsc::Sender<Command>) {
+    thread::spawn(move || {
+        let mut gilrs = match gilrs::Gilrs::new() {
+            Ok(gilrs) => gilrs,
+            Err(err) => {
+                warn!("无法初始化手柄输入: {}", err);
+                return;
+            }
+        };
+
+        loop {
+            while let Some(gilrs::Event { event, .. }) =
+                gilrs.next_event_blocking(Some(GAMEPAD_WAIT_TIMEOUT))
+            {
+                let event = match event {
+                    gilrs::EventType::ButtonPressed(button, _) => {
+                        InputEvent::Press(Trigger::Gamepad(button))
+                    }
+                    gilrs::EventType::ButtonReleased(button, _) => {
+                        InputEvent::Release(Trigger::Gamepad(button))
+                    }
+                    _ => continue,
+                };
+                if sender
+                    .send(Command::Input(event, time::Instant::now()))
+                    .is_err()
+                {
+                    return;
+                }
+            }
+        }
+    });
+}
@@ -0,0 +1,402 @@
+// phasutils: 为Steam游戏恐鬼症设计的实用工具
+// Copyright (C) 2024  Chen Siyuan
+//
+// This program is free software: you can redistribute it and/or modify
+// it under the terms of the GNU General Public License as published by
+// the Free Software Foundation, either version 3 of the License, or
+// (at your option) any later version.
+//
+// This program is distributed in the hope that it will be useful,
+// but WITHOUT ANY WARRANTY; without even the implied warranty of
+// MERCHANTABILITY or FITNESS FOR A PARTICULAR PURPOSE.  See the
+// GNU General Public License for more details.
+//
+// You should have received a copy of the GNU General Public License
+// along with this program.  If not, see <https://www.gnu.org/licenses/>.
+
+use std::time;
+
+use serde::{Deserialize, Serialize};
+
+// 超过这个间隔的点击视为新的一次测速
+const MAX_TAP_GAP: time::Duration = time::Duration::from_secs(2);
+// 计算当前速度时使用的最近间隔数
+const RECENT_INTERVALS: usize = 6;
+// 鬼魂每一步的距离，约 120 BPM 对应常速 1.7 m/s
+const METERS_PER_STEP: f32 = 0.85;
+const SLOW_THRESHOLD: f32 = 1.5;
+const FAST_THRESHOLD: f32 = 1.9;
+// 后段步频比前段快这么多时认为有视野加速
+const ACCELERATION_RATIO: f32 = 1.15;
+// 测速与速度数据比较时允许的误差
+const SPEED_TOLERANCE: f32 = 0.1;
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum SpeedClass {
+    Slow,
+    Normal,
+    Fast,
+}
+
+impl SpeedClass {
+    pub fn name(&self) -> &'static str {
+        match self {
+            SpeedClass::Slow => "慢速",
+            SpeedClass::Normal => "常速",
+            SpeedClass::Fast => "快速",
+        }
+    }
+}
+
+#[derive(Debug, Clone, Copy)]
+pub struct SpeedEstimate {
+    pub steps_per_second: f32,
+    pub meters_per_second: f32,
+    pub class: SpeedClass,
+    pub accelerating: bool,
+}
+
+// 特殊的速度规则
+#[derive(Serialize, Deserialize, Debug, Clone, Copy, PartialEq, Eq)]
+#[serde(rename_all = "snake_case")]
+pub enum SpeedRule {
+    // 发现玩家后加速
+    Revenant,
+    // 离玩家越近越慢
+    Deogen,
+    // 温度越低越快
+    Hantu,
+    // 理智越低越快
+    Moroi,
+    // 年龄越大越慢
+    Thaye,
+    // 看到玩家时冲刺
+    Jinn,
+    // 靠近电器时加速
+    Raiju,
+    // 本体与分身速度不同
+    TheTwins,
+    // 模仿其他鬼魂
+    Mimic,
+}
+
+impl SpeedRule {
+    // 一次猎杀中速度是否会变化
+    fn varies_within_hunt(&self) -> bool {
+        !matches!(
+            self,
+            SpeedRule::Moroi | SpeedRule::Thaye | SpeedRule::TheTwins
+        )
+    }
+}
+
+#[derive(Serialize, Deserialize, Debug, Clone)]
+pub struct SpeedProfile {
+    // 单位均为 m/s
+    pub base: f32,
+    pub max: f32,
+    // 缺省与 `base` 相同
+    #[serde(default)]
+    pub min: Option<f32>,
+    #[serde(default)]
+    pub line_of_sight: bool,
+    #[serde(default)]
+    pub rule: Option<SpeedRule>,
+}
+
+impl SpeedProfile {
+    pub fn min(&self) -> f32 {
+        self.min.unwrap_or(self.base)
+    }
+
+    pub fn matches(&self, estimate: &SpeedEstimate) -> bool {
+        let speed = estimate.meters_per_second;
+        if speed < self.min() * (1.0 - SPEED_TOLERANCE)
+            || speed > self.max * (1.0 + SPEED_TOLERANCE)
+        {
+            return false;
+        }
+
+        !estimate.accelerating
+            || self.line_of_sight
+            || self.rule.is_some_and(|rule| rule.varies_within_hunt())
+    }
+
+    pub fn description(&self) -> String {
+        let line_of_sight = if self.line_of_sight {
+            "视野加速"
+        } else {
+            "无视野加速"
+        };
+
+        match self.rule {
+            None => format!("{}m/s {}", self.base, line_of_sight),
+            Some(SpeedRule::Revenant) => {
+                format!("未发现玩家{}m/s 发现后{}m/s", self.base, self.max)
+            }
+            Some(SpeedRule::Deogen) => {
+                format!("远处{}m/s 靠近玩家降至{}m/s", self.base, self.min())
+            }
+            Some(SpeedRule::Hantu) => {
+                format!("越冷越快 {}~{}m/s {}", self.min(), self.max, line_of_sight)
+            }
+            Some(SpeedRule::Moroi) => {
+                format!("理智越低越快 {}m/s起 最高{}m/s", self.base, self.max)
+            }
+            Some(SpeedRule::Thaye) => {
+                format!("越老越慢 {}~{}m/s {}", self.base, self.min(), line_of_sight)
+            }
+            Some(SpeedRule::Jinn) => {
+                format!("{}m/s 电闸开启时看到玩家冲刺", self.base)
+            }
+            Some(SpeedRule::Raiju) => {
+                format!("{}m/s 靠近电器加速", self.base)
+            }
+            Some(SpeedRule::TheTwins) => {
+                format!("{}m/s 分身更快 {}", self.base, line_of_sight)
+            }
+            Some(SpeedRule::Mimic) => {
+                format!("模仿其他鬼魂 {}~{}m/s", self.min(), self.max)
+            }
+        }
+    }
+}
+
+pub struct TapTempo {
+    taps: Vec<time::Instant>,
+}
+
+impl Default for TapTempo {
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
+impl TapTempo {
+    pub fn new() -> TapTempo {
+        TapTempo { taps: vec![] }
+    }
+
+    pub fn tap(&mut self, at: time::Instant) {
+        if let Some(last) = self.taps.last() {
+            if at.duration_since(*last) > MAX_TAP_GAP {
+                self.taps.clear();
+            }
+        }
+
+        self.taps.push(at);
+    }
+
+    pub fn clear(&mut self) {
+        self.taps.clear();
+    }
+
+    fn steps_per_second(taps: &[time::Instant]) -> f32 {
+        let duration = taps[taps.len() - 1].duration_since(taps[0]).as_secs_f32();
+        if duration > 0.0 {
+            (taps.len() - 1) as f32 / duration
+        } else {
+            0.0
+        }
+    }
+
+    // 至少需要三次点击
+    pub fn estimate(&self) -> Option<SpeedEstimate> {
+        if self.taps.len() < 3 {
+            return None;
+        }
+
+        let recent = &self.taps[self.taps.len().saturating_sub(RECENT_INTERVALS + 1)..];
+        let steps_per_second = TapTempo::steps_per_second(recent);
+        let meters_per_second = steps_per_second * METERS_PER_STEP;
+
+        let class = if meters_per_second < SLOW_THRESHOLD {
+            SpeedClass::Slow
+        } else if meters_per_second > FAST_THRESHOLD {
+            SpeedClass::Fast
+        } else {
+            SpeedClass::Normal
+        };
+
+        // 比较前后两段的步频
+        let accelerating = self.taps.len() >= 6 && {
+            let third = self.taps.len() / 3;
+            let early = TapTempo::steps_per_second(&self.taps[..=third]);
+            let late = TapTempo::steps_per_second(&self.taps[self.taps.len() - 1 - third..]);
+            early > 0.0 && late >= early * ACCELERATION_RATIO
+        };
+
+        Some(SpeedEstimate {
+            steps_per_second,
+            meters_per_second,
+            class,
+            accelerating,
+        })
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    // 从同一个起点按给定间隔（毫秒）依次点击
+    fn tempo(intervals: &[u64]) -> TapTempo {
+        let start = time::Instant::now();
+        let mut tempo = TapTempo::new();
+        let mut at = start;
+        tempo.tap(at);
+        for interval in intervals {
+            at += time::Duration::from_millis(*interval);
+            tempo.tap(at);
+        }
+
+        tempo
+    }
+
+    fn estimate(meters_per_second: f32, accelerating: bool) -> SpeedEstimate {
+        SpeedEstimate {
+            steps_per_second: meters_per_second / METERS_PER_STEP,
+            meters_per_second,
+            class: SpeedClass::Normal,
+            accelerating,
+        }
+    }
+
+    fn profile(base: f32, max: f32, line_of_sight: bool, rule: Option<SpeedRule>) -> SpeedProfile {
+        SpeedProfile {
+            base,
+            max,
+            min: None,
+            line_of_sight,
+            rule,
+        }
+    }
+
+    #[test]
+    fn needs_three_taps() {
+        assert!(tempo(&[]).estimate().is_none());
+        assert!(tempo(&[500]).estimate().is_none());
+
+        let estimate = tempo(&[500, 500]).estimate().unwrap();
+        assert_eq!(estimate.steps_per_second, 2.0);
+        assert!((estimate.meters_per_second - 1.7).abs() < 1e-4);
+        assert_eq!(estimate.class, SpeedClass::Normal);
+    }
+
+    #[test]
+    fn long_gap_starts_over() {
+        assert!(tempo(&[500, 500, 2001]).estimate().is_none());
+        assert!(tempo(&[500, 500, 2001, 500]).estimate().is_none());
+        assert!(tempo(&[500, 500, 2001, 500, 500]).estimate().is_some());
+        // 正好两秒仍然算同一次测速
+        assert!(tempo(&[2000, 2000]).estimate().is_some());
+
+        let mut tempo = tempo(&[500, 500]);
+        tempo.clear();
+        assert!(tempo.estimate().is_none());
+    }
+
+    #[test]
+    fn classify_speed() {
+        for (interval, class) in [
+            (600, SpeedClass::Slow),
+            (570, SpeedClass::Slow),
+            (560, SpeedClass::Normal),
+            (450, SpeedClass::Normal),
+            (445, SpeedClass::Fast),
+            (400, SpeedClass::Fast),
+        ] {
+            assert_eq!(
+                tempo(&[interval; 3]).estimate().unwrap().class,
+                class,
+                "{}ms",
+                interval
+            );
+        }
+    }
+
+    #[test]
+    fn only_recent_intervals_count() {
+        let estimate = tempo(&[1000, 1000, 1000, 1000, 500, 500, 500, 500, 500, 500])
+            .estimate()
+            .unwrap();
+        assert_eq!(estimate.steps_per_second, 2.0);
+    }
+
+    #[test]
+    fn detect_acceleration() {
+        assert!(
+            tempo(&[600, 600, 600, 500, 450])
+                .estimate()
+                .unwrap()
+                .accelerating
+        );
+        // 少于六次点击不判断加速
+        assert!(
+            !tempo(&[600, 600, 500, 450])
+                .estimate()
+                .unwrap()
+                .accelerating
+        );
+        assert!(!tempo(&[500; 5]).estimate().unwrap().accelerating);
+        // 后段只快了约 11%
+        assert!(
+            !tempo(&[500, 500, 500, 450, 450])
+                .estimate()
+                .unwrap()
+                .accelerating
+        );
+    }
+
+    #[test]
+    fn profile_matches_within_tolerance() {
+        let normal = profile(1.7, 1.7, false, None);
+        assert!(normal.matches(&estimate(1.7, false)));
+        assert!(normal.matches(&estimate(1.55, false)));
+        assert!(normal.matches(&estimate(1.85, false)));
+        assert!(!normal.matches(&estimate(1.5, false)));
+        assert!(!normal.matches(&estimate(1.9, false)));
+
+        let deogen = SpeedProfile {
+            min: Some(0.4),
+            ..profile(3.0, 3.0, false, Some(SpeedRule::Deogen))
+        };
+        assert!(deogen.matches(&estimate(0.5, false)));
+        assert!(!deogen.matches(&estimate(0.3, false)));
+    }
+
+    #[test]
+    fn acceleration_needs_line_of_sight_or_rule() {
+        let accelerating = estimate(1.7, true);
+        assert!(!profile(1.7, 2.8, false, None).matches(&accelerating));
+        assert!(profile(1.7, 2.8, true, None).matches(&accelerating));
+        assert!(profile(1.0, 3.0, false, Some(SpeedRule::Revenant)).matches(&accelerating));
+        // 理智影响的速度在一次猎杀中不变
+        assert!(!profile(1.5, 3.71, false, Some(SpeedRule::Moroi)).matches(&accelerating));
+    }
+
+    #[test]
+    fn describe_profile() {
+        assert_eq!(
+            profile(1.7, 2.8, true, None).description(),
+            "1.7m/s 视野加速"
+        );
+        assert_eq!(
+            profile(1.7, 1.7, false, None).description(),
+            "1.7m/s 无视野加速"
+        );
+        assert_eq!(
+            profile(1.0, 3.0, false, Some(SpeedRule::Revenant)).description(),
+            "未发现玩家1m/s 发现后3m/s"
+        );
+        assert_eq!(
+            profile(1.4, 2.7, false, Some(SpeedRule::Hantu)).description(),
+            "越冷越快 1.4~2.7m/s 无视野加速"
+        );
+        let deogen = SpeedProfile {
+            min: Some(0.4),
+            ..profile(3.0, 3.0, false, Some(SpeedRule::Deogen))
+        };
+        assert_eq!(deogen.description(), "远处3m/s 靠近玩家降至0.4m/s");
+    }
+}
@@ -0,0 +1,266 @@
+// phasutils: 为Steam游戏恐鬼症设计的实用工具
+// Copyright (C) 2024  Chen Siyuan
+//
+// This program is free software: you can redistribute it and/or modify
+// it under the terms of the GNU General Public License as published by
+// the Free Software Foundation, either version 3 of the License, or
+// (at your option) any later version.
+//
+// This program is distributed in the hope that it will be useful,
+// but WITHOUT ANY WARRANTY; without even the implied warranty of
+// MERCHANTABILITY or FITNESS FOR A PARTICULAR PURPOSE.  See the
+// GNU General Public License for more details.
+//
+// You should have received a copy of the GNU General Public License
+// along with this program.  If not, see <https://www.gnu.org/licenses/>.
+
+use std::{fmt, str};
+
+use serde::{de, Deserialize, Deserializer, Serialize, Serializer};
+
+// 配置文件中写作 "#rrggbb" 或 "#rrggbbaa"
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct Color {
+    pub r: u8,
+    pub g: u8,
+    pub b: u8,
+    pub a: u8,
+}
+
+impl Color {
+    pub const fn rgb(r: u8, g: u8, b: u8) -> Color {
+        Color { r, g, b, a: 0xff }
+    }
+
+    pub const fn rgba(r: u8, g: u8, b: u8, a: u8) -> Color {
+        Color { r, g, b, a }
+    }
+
+    // 分层窗口和 ARGB 视觉都要求颜色分量预先乘以透明度
+    pub fn premultiplied(self) -> Color {
+        let multiply = |channel: u8| ((channel as u32 * self.a as u32 + 127) / 255) as u8;
+        Color {
+            r: multiply(self.r),
+            g: multiply(self.g),
+            b: multiply(self.b),
+            a: self.a,
+        }
+    }
+}
+
+impl fmt::Display for Color {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(f, "#{:02x}{:02x}{:02x}", self.r, self.g, self.b)?;
+        if self.a != 0xff {
+            write!(f, "{:02x}", self.a)?;
+        }
+
+        Ok(())
+    }
+}
+
+impl str::FromStr for Color {
+    type Err = String;
+
+    fn from_str(text: &str) -> Result<Self, Self::Err> {
+        let hex = text
+            .strip_prefix('#')
+            .filter(|hex| (hex.len() == 6 || hex.len() == 8) && hex.is_ascii())
+            .ok_or_else(|| format!("颜色 {} 应写作 #rrggbb 或 #rrggbbaa", text))?;
+        let channel = |index: usize| {
+            u8::from_str_radix(&hex[index..index + 2], 16)
+                .map_err(|_| format!("无效的颜色 {}", text))
+        };
+
+        Ok(Color {
+            r: channel(0)?,
+            g: channel(2)?,
+            b: channel(4)?,
+            a: if hex.len() == 8 { channel(6)? } else { 0xff },
+        })
+    }
+}
+
+impl Serialize for Color {
+    fn serialize<S: Serializer>(&self, serializer: S) -> Result<S::Ok, S::Error> {
+        serializer.serialize_str(&self.to_string())
+    }
+}
+
+impl<'de> Deserialize<'de> for Color {
+    fn deserialize<D: Deserializer<'de>>(deserializer: D) -> Result<Self, D::Error> {
+        String::deserialize(deserializer)?
+            .parse()
+            .map_err(de::Error::custom)
+    }
+}
+
+#[derive(Serialize, Deserialize, Debug, Clone, PartialEq)]
+pub struct Shadow {
+    pub color: Color,
+    // 与窗口尺寸一样会乘以 `scale`
+    pub offset_x: f32,
+    pub offset_y: f32,
+}
+
+// 每组文字后面的圆角底板
+#[derive(Serialize, Deserialize, Debug, Clone, PartialEq)]
+pub struct Panel {
+    pub color: Color,
+    // 以下两项会乘以 `scale`
+    pub radius: f32,
+    pub padding: f32,
+}
+
+#[derive(Serialize, Deserialize, Debug, Clone, PartialEq)]
+pub struct Theme {
+    pub text: Color,
+    pub highlight: Color,
+    pub warning: Color,
+    pub excluded: Color,
+    // 整个窗口的背景色，透明度为 0 时只显示文字和底板
+    pub background: Color,
+    pub outline: Color,
+    // 为 0 时不描边，会乘以 `scale`
+    #[serde(default)]
+    pub outline_thickness: f32,
+    #[serde(default)]
+    pub shadow: Option<Shadow>,
+    #[serde(default)]
+    pub panel: Option<Panel>,
+}
+
+pub const DEFAULT_THEME: &str = "default";
+pub const BUILTIN_THEMES: [&str; 4] = [DEFAULT_THEME, "bright_map", "red_green", "blue_yellow"];
+
+const TRANSPARENT: Color = Color::rgba(0x00, 0x00, 0x00, 0x00);
+const BLACK: Color = Color::rgb(0x00, 0x00, 0x00);
+
+impl Default for Theme {
+    fn default() -> Self {
+        Theme::builtin(DEFAULT_THEME).unwrap()
+    }
+}
+
+impl Theme {
+    pub fn builtin(name: &str) -> Option<Theme> {
+        let theme = match name {
+            DEFAULT_THEME => Theme {
+                text: Color::rgb(0x66, 0xcc, 0xff),
+                highlight: Color::rgb(0xff, 0xd7, 0x00),
+                warning: Color::rgb(0xff, 0x45, 0x45),
+                excluded: Color::rgb(0x66, 0x66, 0x66),
+                background: TRANSPARENT,
+                outline: BLACK,
+                outline_thickness: 0.5,
+                shadow: None,
+                panel: None,
+            },
+            // 亮色地图上用白字、阴影和半透明底板
+            "bright_map" => Theme {
+                text: Color::rgb(0xff, 0xff, 0xff),
+                highlight: Color::rgb(0xff, 0xd7, 0x00),
+                warning: Color::rgb(0xff, 0x45, 0x45),
+                excluded: Color::rgb(0x99, 0x99, 0x99),
+                background: TRANSPARENT,
+                outline: BLACK,
+                outline_thickness: 1.0,
+                shadow: Some(Shadow {
+                    color: Color::rgba(0x00, 0x00, 0x00, 0x99),
+                    offset_x: 1.0,
+                    offset_y: 1.0,
+                }),
+                panel: Some(Panel {
+                    color: Color::rgba(0x00, 0x00, 0x00, 0x80),
+                    radius: 4.0,
+                    padding: 3.0,
+                }),
+            },
+            // 红绿色盲：Okabe-Ito 配色中的天蓝、黄和红紫
+            "red_green" => Theme {
+                text: Color::rgb(0x56, 0xb4, 0xe9),
+                highlight: Color::rgb(0xf0, 0xe4, 0x42),
+                warning: Color::rgb(0xcc, 0x79, 0xa7),
+                excluded: Color::rgb(0x80, 0x80, 0x80),
+                background: TRANSPARENT,
+                outline: BLACK,
+                outline_thickness: 0.5,
+                shadow: None,
+                panel: None,
+            },
+            // 蓝黄色盲：白、朱红和蓝绿
+            "blue_yellow" => Theme {
+                text: Color::rgb(0xf0, 0xf0, 0xf0),
+                highlight: Color::rgb(0xd5, 0x5e, 0x00),
+                warning: Color::rgb(0x00, 0x9e, 0x73),
+                excluded: Color::rgb(0x80, 0x80, 0x80),
+                background: TRANSPARENT,
+                outline: BLACK,
+                outline_thickness: 0.5,
+                shadow: None,
+                panel: None,
+            },
+            _ => return None,
+        };
+
+        Some(theme)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn parse_colors() {
+        assert_eq!("#66ccff".parse(), Ok(Color::rgb(0x66, 0xcc, 0xff)));
+        assert_eq!(
+            "#00000080".parse(),
+            Ok(Color {
+                r: 0,
+                g: 0,
+                b: 0,
+                a: 0x80
+            })
+        );
+        assert!("66ccff".parse::<Color>().is_err());
+        assert!("#66ccf".parse::<Color>().is_err());
+        assert!("#66ccfg".parse::<Color>().is_err());
+    }
+
+    #[test]
+    fn colors_round_trip() {
+        for text in ["#66ccff", "#10101080"] {
+            assert_eq!(text.parse::<Color>().unwrap().to_string(), text);
+        }
+    }
+
+    #[test]
+    fn premultiply_colors() {
+        assert_eq!(
+            Color::rgba(0xff, 0x80, 0x00, 0x80).premultiplied(),
+            Color::rgba(0x80, 0x40, 0x00, 0x80)
+        );
+        assert_eq!(
+            Color::rgb(0x66, 0xcc, 0xff).premultiplied(),
+            Color::rgb(0x66, 0xcc, 0xff)
+        );
+        assert_eq!(
+            Color::rgba(0xff, 0xff, 0xff, 0).premultiplied(),
+            TRANSPARENT
+        );
+    }
+
+    #[test]
+    fn builtin_themes_keep_background_transparent() {
+        for name in BUILTIN_THEMES {
+            let theme = Theme::builtin(name).unwrap();
+            assert_eq!(theme.background.a, 0, "{}", name);
+            assert!(
+                theme.text.a == 0xff && theme.highlight.a == 0xff,
+                "{}",
+                name
+            );
+        }
+    }
+}
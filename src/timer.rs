@@ -0,0 +1,619 @@
+// phasutils: 为Steam游戏恐鬼症设计的实用工具
+// Copyright (C) 2024  Chen Siyuan
+//
+// This program is free software: you can redistribute it and/or modify
+// it under the terms of the GNU General Public License as published by
+// the Free Software Foundation, either version 3 of the License, or
+// (at your option) any later version.
+//
+// This program is distributed in the hope that it will be useful,
+// but WITHOUT ANY WARRANTY; without even the implied warranty of
+// MERCHANTABILITY or FITNESS FOR A PARTICULAR PURPOSE.  See the
+// GNU General Public License for more details.
+//
+// You should have received a copy of the GNU General Public License
+// along with this program.  If not, see <https://www.gnu.org/licenses/>.
+
+use std::time;
+
+use log::warn;
+
+use crate::config::{CountdownPreset, TimerConfig, TimerThreshold};
+use crate::input::Hotkey;
+
+#[derive(Debug, Clone, Copy)]
+pub struct Lap {
+    // 记录时的总时间
+    pub split: time::Duration,
+    // 距上一次记录（或重置）的时间
+    pub lap: time::Duration,
+}
+
+pub struct StopWatch {
+    elapsed: time::Duration,
+    start: bool,
+    instant: time::Instant,
+    laps: Vec<Lap>,
+    lap_mark: time::Duration,
+}
+
+impl Default for StopWatch {
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
+impl StopWatch {
+    pub fn new() -> StopWatch {
+        StopWatch {
+            elapsed: time::Duration::new(0, 0),
+            start: false,
+            instant: time::Instant::now(),
+            laps: vec![],
+            lap_mark: time::Duration::new(0, 0),
+        }
+    }
+
+    // 停在给定时间的秒表，测试时不依赖真实时间
+    #[cfg(test)]
+    pub fn stopped_at(elapsed: time::Duration) -> StopWatch {
+        StopWatch {
+            elapsed,
+            ..StopWatch::new()
+        }
+    }
+
+    pub fn start(&mut self) {
+        if !self.start {
+            self.start = true;
+            self.instant = time::Instant::now();
+        }
+    }
+
+    pub fn stop(&mut self) {
+        if self.start {
+            self.start = false;
+            self.elapsed += self.instant.elapsed();
+        }
+    }
+
+    pub fn reset(&mut self) {
+        if !self.start {
+            self.elapsed = time::Duration::new(0, 0);
+            self.instant = time::Instant::now();
+            self.lap_mark = time::Duration::new(0, 0);
+        }
+    }
+
+    pub fn lap(&mut self) {
+        let split = self.elapsed();
+        self.laps.push(Lap {
+            split,
+            lap: split - self.lap_mark,
+        });
+        self.lap_mark = split;
+    }
+
+    pub fn laps(&self) -> &[Lap] {
+        &self.laps
+    }
+
+    pub fn clear_laps(&mut self) {
+        self.laps.clear();
+    }
+
+    pub fn is_running(&self) -> bool {
+        self.start
+    }
+
+    pub fn elapsed(&self) -> time::Duration {
+        self.elapsed
+            + if self.start {
+                self.instant.elapsed()
+            } else {
+                time::Duration::new(0, 0)
+            }
+    }
+}
+
+pub fn format_duration(duration: time::Duration, show_tenths: bool) -> String {
+    let tenths = duration.as_millis() / 100;
+    if show_tenths {
+        format!(
+            "{:02}:{:02}.{}",
+            tenths / 600,
+            tenths / 10 % 60,
+            tenths % 10
+        )
+    } else {
+        format!("{:02}:{:02}", tenths / 600, tenths / 10 % 60)
+    }
+}
+
+// 越过阈值后闪烁的时长
+const FLASH_DURATION: time::Duration = time::Duration::from_secs(3);
+// 闪烁时颜色切换的间隔
+pub const FLASH_INTERVAL: time::Duration = time::Duration::from_millis(250);
+
+pub struct Timer {
+    config: TimerConfig,
+    // 倒计时预设，为空时正向计时
+    presets: Vec<CountdownPreset>,
+    preset_index: usize,
+    // 分段列表从最新一条向前滚动的条数
+    lap_scroll: usize,
+    stopwatch: StopWatch,
+}
+
+impl Timer {
+    pub fn new(config: TimerConfig, presets: &[CountdownPreset]) -> Timer {
+        let presets = config
+            .presets
+            .iter()
+            .filter_map(|id| {
+                let preset = presets.iter().find(|preset| &preset.id == id);
+                if preset.is_none() {
+                    warn!("计时器 {} 引用了不存在的倒计时预设 {}", config.id, id);
+                }
+                preset.cloned()
+            })
+            .collect();
+
+        Timer {
+            config,
+            presets,
+            preset_index: 0,
+            lap_scroll: 0,
+            stopwatch: StopWatch::new(),
+        }
+    }
+
+    pub fn config(&self) -> &TimerConfig {
+        &self.config
+    }
+
+    pub fn preset(&self) -> Option<&CountdownPreset> {
+        self.presets.get(self.preset_index)
+    }
+
+    // 按键属于这个计时器时返回 true
+    pub fn handle_hotkey(&mut self, hotkey: Hotkey) -> bool {
+        let key = Some(hotkey);
+        if key == self.config.start {
+            self.stopwatch.start();
+        } else if key == self.config.stop {
+            self.stopwatch.stop();
+        } else if key == self.config.reset {
+            self.stopwatch.reset();
+        } else if key == self.config.restart {
+            self.stopwatch.stop();
+            self.stopwatch.reset();
+            self.stopwatch.start();
+        } else if key == self.config.next_preset && !self.presets.is_empty() {
+            self.stopwatch.stop();
+            self.stopwatch.reset();
+            self.preset_index = (self.preset_index + 1) % self.presets.len();
+        } else if key == self.config.lap {
+            self.stopwatch.lap();
+            self.lap_scroll = 0;
+        } else if key == self.config.clear_laps {
+            self.stopwatch.clear_laps();
+            self.lap_scroll = 0;
+        } else if key == self.config.lap_scroll_up {
+            let max_scroll = self
+                .stopwatch
+                .laps()
+                .len()
+                .saturating_sub(self.config.visible_laps);
+            self.lap_scroll = (self.lap_scroll + 1).min(max_scroll);
+        } else if key == self.config.lap_scroll_down {
+            self.lap_scroll = self.lap_scroll.saturating_sub(1);
+        } else {
+            return false;
+        }
+
+        true
+    }
+
+    // 倒计时归零后不再算作运行，不会一直停在 00:00 占用界面
+    pub fn is_running(&self) -> bool {
+        self.stopwatch.is_running() && !self.is_expired()
+    }
+
+    pub fn is_expired(&self) -> bool {
+        match self.preset() {
+            Some(preset) => self.elapsed() >= time::Duration::from_secs(preset.seconds),
+            None => false,
+        }
+    }
+
+    pub fn elapsed(&self) -> time::Duration {
+        self.stopwatch.elapsed()
+    }
+
+    // 倒计时返回剩余时间，正向计时返回经过的时间
+    pub fn display_time(&self) -> time::Duration {
+        match self.preset() {
+            Some(preset) => {
+                time::Duration::from_secs(preset.seconds).saturating_sub(self.elapsed())
+            }
+            None => self.elapsed(),
+        }
+    }
+
+    pub fn thresholds(&self) -> &[TimerThreshold] {
+        match self.preset() {
+            Some(preset) => &preset.thresholds,
+            None => &self.config.thresholds,
+        }
+    }
+
+    // 越过阈值时的经过时间；倒计时的阈值是剩余秒数
+    fn threshold_elapsed(&self, threshold: &TimerThreshold) -> time::Duration {
+        match self.preset() {
+            Some(preset) => {
+                time::Duration::from_secs(preset.seconds.saturating_sub(threshold.seconds))
+            }
+            None => time::Duration::from_secs(threshold.seconds),
+        }
+    }
+
+    fn passed(&self) -> impl Iterator<Item = &TimerThreshold> {
+        let elapsed = self.elapsed();
+        self.thresholds()
+            .iter()
+            .filter(move |threshold| elapsed >= self.threshold_elapsed(threshold))
+    }
+
+    pub fn passed_thresholds(&self) -> usize {
+        self.passed().count()
+    }
+
+    pub fn is_flashing(&self) -> bool {
+        let elapsed = self.elapsed();
+        // 归零时的阈值在倒计时结束后照样闪烁
+        self.stopwatch.is_running()
+            && self
+                .passed()
+                .any(|threshold| elapsed - self.threshold_elapsed(threshold) < FLASH_DURATION)
+    }
+
+    // 显示内容下一次变化前的时间，停止或归零后只剩闪烁会变化
+    pub fn next_change(&self) -> Option<time::Duration> {
+        let elapsed = self.elapsed().as_nanos();
+        let unit = if self.config.show_tenths {
+            100_000_000
+        } else {
+            1_000_000_000
+        };
+        let mut next = self.is_running().then(|| unit - elapsed % unit);
+        if self.is_flashing() {
+            let interval = FLASH_INTERVAL.as_nanos();
+            let flash = interval - elapsed % interval;
+            next = Some(next.map_or(flash, |next| next.min(flash)));
+        }
+
+        next.map(|next| time::Duration::from_nanos(next as u64))
+    }
+
+    pub fn text(&self) -> String {
+        let mut display_time = self.display_time();
+        // 倒计时向上取整，归零时正好显示 00:00
+        if self.preset().is_some() {
+            let unit = if self.config.show_tenths {
+                100_000_000
+            } else {
+                1_000_000_000
+            };
+            display_time =
+                time::Duration::from_nanos((display_time.as_nanos() as u64).div_ceil(unit) * unit);
+        }
+        let mut text = format_duration(display_time, self.config.show_tenths);
+
+        let name = match self.preset() {
+            Some(preset) if self.config.name.is_empty() => preset.name.clone(),
+            Some(preset) => format!("{}({})", self.config.name, preset.name),
+            None => self.config.name.clone(),
+        };
+        if !name.is_empty() {
+            text = format!("{} {}", name, text);
+        }
+
+        if let Some(threshold) = self
+            .passed()
+            .max_by_key(|threshold| self.threshold_elapsed(threshold))
+        {
+            text = format!("{} {}", text, threshold.label);
+        }
+
+        text
+    }
+
+    // 最新的分段在最上面
+    pub fn lap_lines(&self) -> Vec<String> {
+        self.stopwatch
+            .laps()
+            .iter()
+            .enumerate()
+            .rev()
+            .skip(self.lap_scroll)
+            .take(self.config.visible_laps)
+            .map(|(index, lap)| {
+                format!(
+                    "#{} {} +{}",
+                    index + 1,
+                    format_duration(lap.split, self.config.show_tenths),
+                    format_duration(lap.lap, self.config.show_tenths)
+                )
+            })
+            .collect()
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn threshold(seconds: u64, label: &str) -> TimerThreshold {
+        TimerThreshold {
+            seconds,
+            label: label.to_string(),
+        }
+    }
+
+    fn timer_config(name: &str) -> TimerConfig {
+        TimerConfig {
+            name: name.to_string(),
+            ..Default::default()
+        }
+    }
+
+    fn smudge_config() -> TimerConfig {
+        TimerConfig {
+            restart: Some(rdev::Key::Num4.into()),
+            thresholds: vec![
+                threshold(60, "恶魔可猎杀"),
+                threshold(90, "除魂魄外可猎杀"),
+                threshold(180, "魂魄可猎杀"),
+            ],
+            ..timer_config("圣木")
+        }
+    }
+
+    fn preset(
+        id: &str,
+        name: &str,
+        seconds: u64,
+        thresholds: Vec<TimerThreshold>,
+    ) -> CountdownPreset {
+        CountdownPreset {
+            id: id.to_string(),
+            name: name.to_string(),
+            seconds,
+            thresholds,
+        }
+    }
+
+    fn stop_at(timer: &mut Timer, elapsed: time::Duration) {
+        timer.stopwatch = StopWatch::stopped_at(elapsed);
+    }
+
+    // 从给定时间继续走
+    fn run_from(timer: &mut Timer, elapsed: time::Duration) {
+        stop_at(timer, elapsed);
+        timer.stopwatch.start();
+    }
+
+    #[test]
+    fn smudge_thresholds() {
+        let mut timer = Timer::new(smudge_config(), &[]);
+        for (seconds, text, passed) in [
+            (59, "圣木 00:59", 0),
+            (60, "圣木 01:00 恶魔可猎杀", 1),
+            (90, "圣木 01:30 除魂魄外可猎杀", 2),
+            (179, "圣木 02:59 除魂魄外可猎杀", 2),
+            (180, "圣木 03:00 魂魄可猎杀", 3),
+        ] {
+            stop_at(&mut timer, time::Duration::from_secs(seconds));
+            assert_eq!(timer.text(), text);
+            assert_eq!(timer.passed_thresholds(), passed);
+        }
+    }
+
+    #[test]
+    fn restart_key() {
+        let mut timer = Timer::new(smudge_config(), &[]);
+        stop_at(&mut timer, time::Duration::from_secs(200));
+        assert!(!timer.handle_hotkey(rdev::Key::Num1.into()));
+        assert!(!timer.is_running());
+
+        assert!(timer.handle_hotkey(rdev::Key::Num4.into()));
+        assert!(timer.is_running());
+        assert_eq!(timer.passed_thresholds(), 0);
+    }
+
+    #[test]
+    fn countdown_rounds_up() {
+        let cooldown = preset(
+            "cooldown",
+            "猎杀冷却",
+            25,
+            vec![threshold(5, "即将结束"), threshold(0, "可再次猎杀")],
+        );
+        let config = TimerConfig {
+            presets: vec!["cooldown".to_string()],
+            ..timer_config("")
+        };
+        let mut timer = Timer::new(config, &[cooldown]);
+        for (millis, text) in [
+            (0, "猎杀冷却 00:25"),
+            (100, "猎杀冷却 00:25"),
+            (19_500, "猎杀冷却 00:06"),
+            (20_000, "猎杀冷却 00:05 即将结束"),
+            (24_999, "猎杀冷却 00:01 即将结束"),
+            (25_000, "猎杀冷却 00:00 可再次猎杀"),
+            (30_000, "猎杀冷却 00:00 可再次猎杀"),
+        ] {
+            stop_at(&mut timer, time::Duration::from_millis(millis));
+            assert_eq!(timer.text(), text);
+        }
+    }
+
+    #[test]
+    fn next_preset_switches_countdown() {
+        let presets = [
+            preset("amateur", "业余", 300, vec![threshold(60, "剩余1分钟")]),
+            preset("intermediate", "中级", 120, vec![threshold(30, "剩余30秒")]),
+            preset("unused", "专业", 0, vec![]),
+        ];
+        let config = TimerConfig {
+            presets: vec![
+                "amateur".to_string(),
+                "missing".to_string(),
+                "intermediate".to_string(),
+            ],
+            next_preset: Some(rdev::Key::Num8.into()),
+            ..timer_config("准备阶段")
+        };
+        // 不存在的预设被跳过
+        let mut timer = Timer::new(config, &presets);
+        assert_eq!(timer.text(), "准备阶段(业余) 05:00");
+
+        // 剩余秒数阈值换算成经过的时间
+        stop_at(&mut timer, time::Duration::from_secs(240));
+        assert_eq!(timer.text(), "准备阶段(业余) 01:00 剩余1分钟");
+
+        assert!(timer.handle_hotkey(rdev::Key::Num8.into()));
+        assert_eq!(timer.elapsed(), time::Duration::ZERO);
+        assert_eq!(timer.text(), "准备阶段(中级) 02:00");
+
+        stop_at(&mut timer, time::Duration::from_secs(90));
+        assert_eq!(timer.text(), "准备阶段(中级) 00:30 剩余30秒");
+
+        timer.handle_hotkey(rdev::Key::Num8.into());
+        assert_eq!(timer.text(), "准备阶段(业余) 05:00");
+    }
+
+    #[test]
+    fn flashing_after_threshold() {
+        let mut timer = Timer::new(smudge_config(), &[]);
+        stop_at(&mut timer, time::Duration::from_secs(60));
+        assert!(!timer.is_flashing());
+
+        run_from(&mut timer, time::Duration::from_secs(60));
+        assert!(timer.is_flashing());
+        assert!(timer.next_change().unwrap() <= FLASH_INTERVAL);
+
+        run_from(&mut timer, time::Duration::from_secs(59));
+        assert!(!timer.is_flashing());
+
+        run_from(&mut timer, time::Duration::from_secs(60) + FLASH_DURATION);
+        assert!(!timer.is_flashing());
+    }
+
+    #[test]
+    fn countdown_expires_at_zero() {
+        let cooldown = preset("cooldown", "猎杀冷却", 25, vec![threshold(0, "可再次猎杀")]);
+        let config = TimerConfig {
+            presets: vec!["cooldown".to_string()],
+            restart: Some(rdev::Key::Num5.into()),
+            ..timer_config("")
+        };
+        let mut timer = Timer::new(config, &[cooldown]);
+        run_from(&mut timer, time::Duration::from_millis(24_500));
+        assert!(timer.is_running());
+        assert!(!timer.is_expired());
+        assert!(timer.next_change().unwrap() <= time::Duration::from_millis(500));
+
+        // 归零的阈值还在闪烁，但计时器已经不算运行
+        run_from(&mut timer, time::Duration::from_secs(25));
+        assert!(!timer.is_running());
+        assert!(timer.is_expired());
+        assert!(timer.is_flashing());
+        assert!(timer.next_change().unwrap() <= FLASH_INTERVAL);
+
+        run_from(&mut timer, time::Duration::from_secs(25) + FLASH_DURATION);
+        assert!(!timer.is_running());
+        assert_eq!(timer.next_change(), None);
+        assert_eq!(timer.text(), "猎杀冷却 00:00 可再次猎杀");
+
+        // 重新开始后再次运行
+        assert!(timer.handle_hotkey(rdev::Key::Num5.into()));
+        assert!(timer.is_running());
+        assert!(!timer.is_expired());
+    }
+
+    fn stopwatch_config() -> TimerConfig {
+        TimerConfig {
+            reset: Some(rdev::Key::Num3.into()),
+            show_tenths: true,
+            lap: Some(rdev::Key::Num9.into()),
+            clear_laps: Some(rdev::Key::Minus.into()),
+            lap_scroll_up: Some(rdev::Key::LeftBracket.into()),
+            lap_scroll_down: Some(rdev::Key::RightBracket.into()),
+            ..timer_config("")
+        }
+    }
+
+    #[test]
+    fn format_tenths() {
+        let duration = time::Duration::from_millis(65_990);
+        assert_eq!(format_duration(duration, false), "01:05");
+        assert_eq!(format_duration(duration, true), "01:05.9");
+        assert_eq!(
+            format_duration(time::Duration::from_secs(3600), true),
+            "60:00.0"
+        );
+    }
+
+    #[test]
+    fn laps_survive_reset() {
+        let mut timer = Timer::new(stopwatch_config(), &[]);
+        stop_at(&mut timer, time::Duration::from_millis(10_500));
+        timer.handle_hotkey(rdev::Key::Num9.into());
+        timer.handle_hotkey(rdev::Key::Num9.into());
+        assert_eq!(
+            timer.lap_lines(),
+            ["#2 00:10.5 +00:00.0", "#1 00:10.5 +00:10.5"]
+        );
+
+        // 重置后分段仍然保留，下一段从零开始
+        timer.handle_hotkey(rdev::Key::Num3.into());
+        assert_eq!(timer.elapsed(), time::Duration::ZERO);
+        timer.handle_hotkey(rdev::Key::Num9.into());
+        assert_eq!(timer.lap_lines()[0], "#3 00:00.0 +00:00.0");
+        assert_eq!(timer.lap_lines().len(), 3);
+
+        timer.handle_hotkey(rdev::Key::Minus.into());
+        assert!(timer.lap_lines().is_empty());
+    }
+
+    #[test]
+    fn lap_scroll_is_clamped() {
+        let mut timer = Timer::new(stopwatch_config(), &[]);
+        stop_at(&mut timer, time::Duration::from_secs(1));
+        for _ in 0..5 {
+            timer.handle_hotkey(rdev::Key::Num9.into());
+        }
+        assert_eq!(timer.lap_lines().len(), timer.config().visible_laps);
+        assert!(timer.lap_lines()[0].starts_with("#5 "));
+
+        for _ in 0..5 {
+            timer.handle_hotkey(rdev::Key::LeftBracket.into());
+        }
+        assert!(timer.lap_lines()[0].starts_with("#3 "));
+        assert!(timer.lap_lines()[2].starts_with("#1 "));
+
+        timer.handle_hotkey(rdev::Key::RightBracket.into());
+        assert!(timer.lap_lines()[0].starts_with("#4 "));
+
+        // 新的分段回到最新一条
+        timer.handle_hotkey(rdev::Key::Num9.into());
+        assert!(timer.lap_lines()[0].starts_with("#6 "));
+
+        for _ in 0..5 {
+            timer.handle_hotkey(rdev::Key::RightBracket.into());
+        }
+        assert!(timer.lap_lines()[0].starts_with("#6 "));
+    }
+}
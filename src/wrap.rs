@@ -0,0 +1,194 @@
+// phasutils: 为Steam游戏恐鬼症设计的实用工具
+// Copyright (C) 2024  Chen Siyuan
+//
+// This program is free software: you can redistribute it and/or modify
+// it under the terms of the GNU General Public License as published by
+// the Free Software Foundation, either version 3 of the License, or
+// (at your option) any later version.
+//
+// This program is distributed in the hope that it will be useful,
+// but WITHOUT ANY WARRANTY; without even the implied warranty of
+// MERCHANTABILITY or FITNESS FOR A PARTICULAR PURPOSE.  See the
+// GNU General Public License for more details.
+//
+// You should have received a copy of the GNU General Public License
+// along with this program.  If not, see <https://www.gnu.org/licenses/>.
+
+// 按 UAX #14 的简化规则折行，字宽由调用者提供
+
+// 不能出现在行首的标点
+const NO_LINE_START: &str = "，。、；：？！）》」』】〕〉”’…‥·・ー～％,.;:!?)]}%";
+// 不能出现在行尾的标点
+const NO_LINE_END: &str = "（《「『【〔〈“‘([{";
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+enum Class {
+    Space,
+    Ideographic,
+    Open,
+    Close,
+    Hyphen,
+    Alphabetic,
+}
+
+fn class(c: char) -> Class {
+    if NO_LINE_START.contains(c) {
+        Class::Close
+    } else if NO_LINE_END.contains(c) {
+        Class::Open
+    } else if c == ' ' || c == '\t' {
+        Class::Space
+    } else if c == '-' {
+        Class::Hyphen
+    } else if is_ideographic(c) {
+        Class::Ideographic
+    } else {
+        Class::Alphabetic
+    }
+}
+
+fn is_ideographic(c: char) -> bool {
+    matches!(c,
+        '\u{2E80}'..='\u{9FFF}'
+        | '\u{AC00}'..='\u{D7AF}'
+        | '\u{F900}'..='\u{FAFF}'
+        | '\u{FF00}'..='\u{FFEF}'
+        | '\u{20000}'..='\u{2FFFF}')
+}
+
+// 能否在 `prev` 和 `next` 之间换行
+fn can_break(prev: char, next: char) -> bool {
+    match (class(prev), class(next)) {
+        (_, Class::Close) | (Class::Open, _) => false,
+        (Class::Space, _) => true,
+        (_, Class::Space) => false,
+        (Class::Hyphen, Class::Alphabetic) => true,
+        (Class::Ideographic, _) | (_, Class::Ideographic) => true,
+        // 全角的闭合标点后可以换行，"e.g." 这样的半角标点后不行
+        (Class::Close, _) => !prev.is_ascii(),
+        (_, Class::Open) => !next.is_ascii(),
+        _ => false,
+    }
+}
+
+// 不可拆分的片段，行末的空格不计入宽度
+fn segments(paragraph: &str) -> Vec<&str> {
+    let mut segments = vec![];
+    let mut start = 0;
+    let mut prev = None;
+    for (index, c) in paragraph.char_indices() {
+        if let Some(prev) = prev {
+            if can_break(prev, c) {
+                segments.push(&paragraph[start..index]);
+                start = index;
+            }
+        }
+        prev = Some(c);
+    }
+    if start < paragraph.len() {
+        segments.push(&paragraph[start..]);
+    }
+
+    segments
+}
+
+pub fn wrap<F: FnMut(char) -> f32>(text: &str, width: f32, mut advance: F) -> Vec<String> {
+    let mut lines = vec![];
+    for paragraph in text.split('\n') {
+        let mut line = String::new();
+        let mut line_width = 0.0;
+        for segment in segments(paragraph) {
+            let segment_width: f32 = segment.chars().map(&mut advance).sum();
+            let trimmed_width: f32 = segment.trim_end().chars().map(&mut advance).sum();
+
+            if !line.is_empty() && line_width + trimmed_width > width {
+                lines.push(line.trim_end().to_string());
+                line.clear();
+                line_width = 0.0;
+            }
+
+            // 比整行还宽的单词只能逐字拆开
+            if line.is_empty() && trimmed_width > width {
+                for c in segment.chars() {
+                    let c_width = advance(c);
+                    if !line.is_empty() && line_width + c_width > width {
+                        lines.push(line.trim_end().to_string());
+                        line.clear();
+                        line_width = 0.0;
+                    }
+                    line.push(c);
+                    line_width += c_width;
+                }
+                continue;
+            }
+
+            line.push_str(segment);
+            line_width += segment_width;
+        }
+        lines.push(line.trim_end().to_string());
+    }
+
+    lines
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    // 半角字符宽 1，全角字符宽 2
+    fn advance(c: char) -> f32 {
+        if c.is_ascii() {
+            1.0
+        } else {
+            2.0
+        }
+    }
+
+    #[test]
+    fn words_are_not_split() {
+        assert_eq!(
+            wrap("hello world foo", 11.0, advance),
+            ["hello world", "foo"]
+        );
+        assert_eq!(wrap("hello world", 8.0, advance), ["hello", "world"]);
+    }
+
+    #[test]
+    fn long_words_are_split_by_character() {
+        assert_eq!(wrap("abcdefghij", 4.0, advance), ["abcd", "efgh", "ij"]);
+    }
+
+    #[test]
+    fn hyphen_allows_a_break() {
+        assert_eq!(wrap("line-of-sight", 8.0, advance), ["line-of-", "sight"]);
+    }
+
+    #[test]
+    fn ideographs_break_anywhere() {
+        assert_eq!(wrap("鬼魂会在猎杀时", 8.0, advance), ["鬼魂会在", "猎杀时"]);
+    }
+
+    #[test]
+    fn closing_punctuation_never_starts_a_line() {
+        assert_eq!(wrap("一二三四，五", 8.0, advance), ["一二三", "四，五"]);
+        assert_eq!(wrap("一二三四……", 8.0, advance), ["一二三", "四……"]);
+    }
+
+    #[test]
+    fn opening_punctuation_never_ends_a_line() {
+        assert_eq!(wrap("一二三（四）", 8.0, advance), ["一二三", "（四）"]);
+        assert_eq!(wrap("一二三「四", 8.0, advance), ["一二三", "「四"]);
+    }
+
+    #[test]
+    fn mixed_scripts() {
+        assert_eq!(wrap("使用EMF5检测", 7.0, advance), ["使用", "EMF5检", "测"]);
+        assert_eq!(wrap("通灵盒 DOTS", 8.0, advance), ["通灵盒", "DOTS"]);
+    }
+
+    #[test]
+    fn newlines_and_trailing_spaces() {
+        assert_eq!(wrap("a\n\nb  ", 10.0, advance), ["a", "", "b"]);
+        assert_eq!(wrap("", 10.0, advance), [""]);
+    }
+}